@@ -0,0 +1,206 @@
+// Global allocator that counts allocations per part, only when enabled with `--memory`.
+// Otherwise it forwards straight to the system allocator.
+// The peak resident set size comes from the kernel (Linux only): `VmHWM` in /proc/self/status,
+// set back to the current size at the start of every part through /proc/self/clear_refs.
+
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+pub struct CountingAllocator;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// only between `reset` and `stop`, so formatting an answer is not part of its count
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+// live heap bytes (can go negative when memory from before `enable` is freed)
+static CURRENT: AtomicIsize = AtomicIsize::new(0);
+static BASELINE: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+static PEAK_RSS: AtomicUsize = AtomicUsize::new(0); // 0 when unknown
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    CURRENT.fetch_sub(size as isize, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if COUNTING.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+        }
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AllocStats {
+    pub allocations: usize,
+    pub bytes: usize,
+    #[serde(alias = "peak_bytes")]
+    pub peak_heap_bytes: usize, // most heap memory in use at once, on top of what was live at the start
+    #[serde(default)]
+    pub peak_rss_bytes: Option<usize>, // most memory resident at once, of the whole process
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocs, {} allocated, {} peak heap",
+            self.allocations,
+            format_bytes(self.bytes),
+            format_bytes(self.peak_heap_bytes)
+        )?;
+        if let Some(rss) = self.peak_rss_bytes {
+            write!(f, ", {} peak RSS", format_bytes(rss))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, units[unit])
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Peak resident set size in bytes, from a /proc/self/status like `VmHWM:   1776 kB`.
+fn parse_peak_rss(status: &str) -> Option<usize> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let mut words = line["VmHWM:".len()..].split_whitespace();
+    match (words.next()?.parse::<usize>().ok()?, words.next()) {
+        (kb, Some("kB")) => Some(kb * 1024),
+        _ => None,
+    }
+}
+
+/// Starts a new measurement: counters go back to zero, the peaks to the current sizes.
+pub fn reset() {
+    if !enabled() {
+        return;
+    }
+    // 5 resets the peak resident set size, fails on other systems and older kernels
+    let _ = write("/proc/self/clear_refs", "5");
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    ALLOCATED.store(0, Ordering::Relaxed);
+    let current = CURRENT.load(Ordering::Relaxed);
+    BASELINE.store(current, Ordering::Relaxed);
+    PEAK.store(current, Ordering::Relaxed);
+    PEAK_RSS.store(0, Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+}
+
+/// Ends the measurement started by `reset`, `stats` keeps returning its counts.
+pub fn stop() {
+    if !COUNTING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let rss = read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| parse_peak_rss(&status));
+    PEAK_RSS.store(rss.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Counts of the last measurement, or `None` when counting is disabled.
+pub fn stats() -> Option<AllocStats> {
+    if !enabled() {
+        return None;
+    }
+    Some(AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED.load(Ordering::Relaxed),
+        peak_heap_bytes: (PEAK.load(Ordering::Relaxed) - BASELINE.load(Ordering::Relaxed)).max(0)
+            as usize,
+        peak_rss_bytes: match PEAK_RSS.load(Ordering::Relaxed) {
+            0 => None,
+            rss => Some(rss),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(2048), "2.0KiB");
+        assert_eq!(format_bytes(30_000_000 * 8), "228.9MiB");
+    }
+
+    #[test]
+    fn test_parse_peak_rss() {
+        let status =
+            "Name:\taoc-2020\nVmPeak:\t   12000 kB\nVmHWM:\t    1776 kB\nVmRSS:\t    1700 kB\n";
+        assert_eq!(parse_peak_rss(status), Some(1776 * 1024));
+        assert_eq!(parse_peak_rss("VmRSS:\t1700 kB"), None);
+        assert_eq!(parse_peak_rss("VmHWM:\t1776"), None);
+    }
+
+    #[test]
+    fn test_counts_allocations() {
+        // other tests run in parallel, so only lower bounds are reliable
+        enable();
+        reset();
+        let big = vec![1u8; 1_000_000];
+        stop();
+        let stats = stats().unwrap();
+        assert!(stats.allocations >= 1);
+        assert!(stats.bytes >= 1_000_000);
+        assert!(stats.peak_heap_bytes >= 1_000_000);
+        if cfg!(target_os = "linux") {
+            assert!(stats.peak_rss_bytes.unwrap() >= 1_000_000);
+        }
+        drop(big);
+    }
+}
@@ -0,0 +1,92 @@
+// Known answers kept next to the input instead of in the code, one `part: answer` line per part.
+// Answers the puzzle site rejected are kept as well, so they are never submitted twice:
+//   1: 1801
+//   2 wrong: 1234 # too high
+//   2: 2060
+
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+fn parse_known_answers(content: &str) -> (String, String) {
+    let mut answers = (String::new(), String::new());
+    for line in content.lines() {
+        let mut split = line.splitn(2, ':');
+        let part = split.next().unwrap_or("").trim();
+        let answer = split.next().unwrap_or("").trim().to_string();
+        match part {
+            "1" => answers.0 = answer,
+            "2" => answers.1 = answer,
+            _ => {}
+        }
+    }
+    answers
+}
+
+/// Known answers of both parts, empty when the file or the part is missing.
+pub fn read_known_answers<P: AsRef<Path>>(path: P) -> (String, String) {
+    parse_known_answers(&read_to_string(path).unwrap_or_default())
+}
+
+/// Every answer of `part` that was rejected before.
+pub fn read_wrong_answers<P: AsRef<Path>>(path: P, part: usize) -> Vec<String> {
+    let key = format!("{} wrong", part);
+    read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut split = line.splitn(2, ':');
+            if split.next()?.trim() != key {
+                return None;
+            }
+            // drop the hint
+            Some(split.next()?.split('#').next()?.trim().to_string())
+        })
+        .collect()
+}
+
+fn append_line<P: AsRef<Path>>(path: P, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+pub fn store_answer<P: AsRef<Path>>(path: P, part: usize, answer: &str) -> io::Result<()> {
+    append_line(path, &format!("{}: {}", part, answer))
+}
+
+pub fn store_wrong_answer<P: AsRef<Path>>(
+    path: P,
+    part: usize,
+    answer: &str,
+    hint: Option<&str>,
+) -> io::Result<()> {
+    match hint {
+        Some(hint) => append_line(path, &format!("{} wrong: {} # {}", part, answer, hint)),
+        None => append_line(path, &format!("{} wrong: {}", part, answer)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_answers() {
+        let answers = parse_known_answers("1: 1801\n2: 2060\n");
+        assert_eq!(answers, ("1801".to_string(), "2060".to_string()));
+        let answers = parse_known_answers("2 wrong: 12\n2: 2060");
+        assert_eq!(answers, ("".to_string(), "2060".to_string()));
+    }
+
+    #[test]
+    fn test_store_answers() {
+        let path = std::env::temp_dir().join(format!("aoc-answers-{}.txt", std::process::id()));
+        store_wrong_answer(&path, 1, "1234", Some("too high")).unwrap();
+        store_wrong_answer(&path, 1, "99", None).unwrap();
+        store_answer(&path, 1, "1801").unwrap();
+        assert_eq!(read_wrong_answers(&path, 1), vec!["1234", "99"]);
+        assert!(read_wrong_answers(&path, 2).is_empty());
+        assert_eq!(read_known_answers(&path).0, "1801");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
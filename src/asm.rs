@@ -0,0 +1,391 @@
+// Assembler, disassembler and binary format for handheld programs.
+//
+// The assembly is the puzzle notation plus labels and comments:
+//   start:  acc +1        # comments start with '#' or "//"
+//           jmp start     # a label operand becomes the relative offset to the label
+// ';' separates instructions on one line, so `loop: acc +1 ; jmp loop` is two instructions.
+//
+// The binary format is the magic "HHC1", the number of instructions and then one opcode
+// byte plus argument per instruction. Numbers are LEB128 varints, arguments zigzag encoded.
+
+use crate::day8::parse_program;
+use crate::vm::{Instruction, InstructionSet, Op, JMP};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let end = [line.find('#'), line.find("//")]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// an instruction before its labels are resolved
+struct Statement<'a> {
+    line: usize,
+    op: &'a str,
+    operand: &'a str,
+}
+
+pub fn assemble(source: &str, set: &InstructionSet) -> Result<Vec<Instruction>, AsmError> {
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut statements = Vec::new();
+
+    for (nr, line) in source.lines().enumerate() {
+        let line_nr = nr + 1;
+        for mut statement in strip_comment(line).split(';') {
+            // any number of labels may precede an instruction
+            while let Some(colon) = statement.find(':') {
+                let label = statement[..colon].trim();
+                if !is_label(label) {
+                    return Err(AsmError {
+                        line: line_nr,
+                        message: format!("invalid label '{}'", label),
+                    });
+                }
+                if labels.insert(label, statements.len()).is_some() {
+                    return Err(AsmError {
+                        line: line_nr,
+                        message: format!("label '{}' is defined twice", label),
+                    });
+                }
+                statement = &statement[colon + 1..];
+            }
+            let words: Vec<&str> = statement.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                [op, operand] => statements.push(Statement {
+                    line: line_nr,
+                    op,
+                    operand,
+                }),
+                _ => {
+                    return Err(AsmError {
+                        line: line_nr,
+                        message: format!(
+                            "expected '<opcode> <argument>', got '{}'",
+                            statement.trim()
+                        ),
+                    })
+                }
+            }
+        }
+    }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(pc, statement)| {
+            let op = set.lookup(statement.op).ok_or_else(|| AsmError {
+                line: statement.line,
+                message: format!("unknown opcode '{}'", statement.op),
+            })?;
+            let arg = match statement.operand.parse::<i32>() {
+                Ok(arg) => arg,
+                Err(_) => match labels.get(statement.operand) {
+                    Some(&target) => target as i32 - pc as i32,
+                    None => {
+                        return Err(AsmError {
+                            line: statement.line,
+                            message: format!("unknown label or number '{}'", statement.operand),
+                        })
+                    }
+                },
+            };
+            Ok(Instruction { op, arg })
+        })
+        .collect()
+}
+
+/// Assembly for a program: every `jmp` target gets a label, which `assemble` turns back into
+/// the same offsets. The pc of every instruction is added as a comment.
+/// Fails on an opcode that is not in `set`, as `assemble` could not read it back.
+pub fn disassemble(program: &[Instruction], set: &InstructionSet) -> Result<String, String> {
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter(|(_, ins)| ins.op == JMP)
+        .map(|(pc, ins)| pc as i64 + ins.arg as i64)
+        .filter(|&target| target >= 0 && target <= program.len() as i64)
+        .map(|target| target as usize)
+        .collect();
+    let label = |pc: usize| {
+        if pc == program.len() {
+            "end".to_string()
+        } else {
+            format!("l{}", pc)
+        }
+    };
+
+    let mut asm = String::new();
+    for (pc, ins) in program.iter().enumerate() {
+        let prefix = if targets.contains(&pc) {
+            format!("{}:", label(pc))
+        } else {
+            String::new()
+        };
+        let name = set
+            .name(ins.op)
+            .ok_or_else(|| format!("pc {}: unknown opcode byte {}", pc, ins.op.0))?;
+        let target = pc as i64 + ins.arg as i64;
+        let operand = if ins.op == JMP && target >= 0 && targets.contains(&(target as usize)) {
+            label(target as usize)
+        } else {
+            format!("{:+}", ins.arg)
+        };
+        asm.push_str(&format!("{:<8}{} {:<8}# {}\n", prefix, name, operand, pc));
+    }
+    if targets.contains(&program.len()) {
+        asm.push_str("end:\n");
+    }
+    Ok(asm)
+}
+
+const MAGIC: &[u8; 4] = b"HHC1";
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or("unexpected end of data")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("varint too long".to_string())
+}
+
+pub fn encode(program: &[Instruction]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    write_varint(&mut bytes, program.len() as u64);
+    for ins in program {
+        bytes.push(ins.op.0);
+        // zigzag: small negative numbers stay small
+        let arg = ins.arg as i64;
+        write_varint(&mut bytes, ((arg << 1) ^ (arg >> 63)) as u64);
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err("not a handheld program (bad magic)".to_string());
+    }
+    let mut pos = MAGIC.len();
+    let len = read_varint(bytes, &mut pos)? as usize;
+    let mut program = Vec::with_capacity(len.min(bytes.len()));
+    for _ in 0..len {
+        let op = Op(*bytes.get(pos).ok_or("unexpected end of data")?);
+        pos += 1;
+        let zigzag = read_varint(bytes, &mut pos)?;
+        let arg = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+        let arg = i32::try_from(arg).map_err(|_| format!("argument {} out of range", arg))?;
+        program.push(Instruction { op, arg });
+    }
+    if pos != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - pos));
+    }
+    Ok(program)
+}
+
+/// Loads a program by extension: `.bin` is decoded, `.asm` assembled and anything else
+/// read in the puzzle notation.
+pub fn load_program(path: &Path, set: &InstructionSet) -> Result<Vec<Instruction>, String> {
+    let error = |e: &dyn fmt::Display| format!("{}: {}", path.display(), e);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("bin") => decode(&fs::read(path).map_err(|e| error(&e))?).map_err(|e| error(&e)),
+        Some("asm") => {
+            let source = fs::read_to_string(path).map_err(|e| error(&e))?;
+            assemble(&source, set).map_err(|e| error(&e))
+        }
+        _ => {
+            parse_program(&fs::read_to_string(path).map_err(|e| error(&e))?).map_err(|e| error(&e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::read_program;
+    use crate::vm::{Halt, Machine, ACC, NOP};
+    use std::fs::read_to_string;
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn test_doc_example() {
+        let set = InstructionSet::handheld();
+        // the example at the top of this file
+        let doc = "start:  acc +1        # comments start with '#' or \"//\"\n        jmp start     # a label operand becomes the relative offset to the label\n";
+        assert_eq!(
+            assemble(doc, &set).unwrap(),
+            vec![
+                Instruction { op: ACC, arg: 1 },
+                Instruction { op: JMP, arg: -1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_puzzle_notation() {
+        assert_eq!(
+            parse_program("nop +0\n\nacc +1"),
+            Err("line 2: expected '<opcode> <argument>', got ''".to_string())
+        );
+        assert_eq!(
+            parse_program("nop +0\nacc one"),
+            Err("line 2: invalid argument 'one'".to_string())
+        );
+        let path = std::env::temp_dir().join(format!("asm-blank-line-{}.txt", std::process::id()));
+        fs::write(&path, "nop +0\n\n").unwrap();
+        let error = load_program(&path, &InstructionSet::handheld()).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(error.ends_with(": line 2: expected '<opcode> <argument>', got ''"));
+    }
+
+    #[test]
+    fn test_assemble_labels_and_comments() {
+        let set = InstructionSet::handheld();
+        let source = "# count to three\nstart: acc +1 ; acc +1\n  acc +1 // last one\n  jmp done\n  jmp start\ndone:";
+        let program = assemble(source, &set).unwrap();
+        assert_eq!(
+            program,
+            vec![
+                Instruction { op: ACC, arg: 1 },
+                Instruction { op: ACC, arg: 1 },
+                Instruction { op: ACC, arg: 1 },
+                Instruction { op: JMP, arg: 2 },
+                Instruction { op: JMP, arg: -4 },
+            ]
+        );
+        let mut machine = Machine::new(program, &set);
+        assert_eq!(machine.run(), Halt::Terminated);
+        assert_eq!(machine.regs.acc, 3);
+
+        let program = assemble("loop: acc +1 ; jmp loop", &set).unwrap();
+        assert_eq!(program[1], Instruction { op: JMP, arg: -1 });
+        let program = assemble("nop back\nback: nop -1", &set).unwrap();
+        assert_eq!(program[0], Instruction { op: NOP, arg: 1 });
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let set = InstructionSet::handheld();
+        let error = |source| assemble(source, &set).unwrap_err().to_string();
+        assert_eq!(error("acc +1\nmul +2"), "line 2: unknown opcode 'mul'");
+        assert_eq!(
+            error("jmp nowhere"),
+            "line 1: unknown label or number 'nowhere'"
+        );
+        assert_eq!(
+            error("a: nop +0\na: nop +0"),
+            "line 2: label 'a' is defined twice"
+        );
+        assert_eq!(error("1x: nop +0"), "line 1: invalid label '1x'");
+        assert_eq!(
+            error("acc"),
+            "line 1: expected '<opcode> <argument>', got 'acc'"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_round_trip() {
+        let set = InstructionSet::handheld();
+        let program = read_program(EXAMPLE);
+        let asm = disassemble(&program, &set).unwrap();
+        assert!(asm.starts_with("        nop +0      # 0\n"));
+        assert!(asm.contains("\nl3:     acc +3      # 3\n"));
+        assert!(asm.contains("        jmp l6      # 2\n"));
+        assert_eq!(assemble(&asm, &set).unwrap(), program);
+
+        let program = read_program(&read_to_string("inputs/day8.txt").unwrap());
+        assert_eq!(
+            assemble(&disassemble(&program, &set).unwrap(), &set).unwrap(),
+            program
+        );
+
+        let invalid = vec![Instruction {
+            op: Op::INVALID,
+            arg: 1,
+        }];
+        assert_eq!(
+            disassemble(&invalid, &set),
+            Err(format!("pc 0: unknown opcode byte {}", Op::INVALID.0))
+        );
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let program = read_program(EXAMPLE);
+        let bytes = encode(&program);
+        assert_eq!(&bytes[..5], b"HHC1\x09");
+        assert_eq!(bytes.len(), 5 + 2 * 9 + 1); // only -99 needs a second byte
+        assert_eq!(decode(&bytes).unwrap(), program);
+
+        let extremes = vec![
+            Instruction {
+                op: ACC,
+                arg: i32::MIN,
+            },
+            Instruction {
+                op: Op::INVALID,
+                arg: i32::MAX,
+            },
+            Instruction { op: NOP, arg: -1 },
+        ];
+        assert_eq!(decode(&encode(&extremes)).unwrap(), extremes);
+
+        let program = read_program(&read_to_string("inputs/day8.txt").unwrap());
+        assert_eq!(decode(&encode(&program)).unwrap(), program);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(decode(b"nop +0").is_err());
+        let bytes = encode(&read_program(EXAMPLE));
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+// Control flow graph of a handheld program (`InstructionSet::handheld`).
+// `jmp` jumps by its argument, `acc` and `nop` fall through to the next one.
+// Any other opcode is invalid and halts the program there, just like the VM does.
+// Node `len` is the virtual end of the program: reaching it means terminating.
+
+use crate::vm::{Instruction, InstructionSet, ACC, JMP, NOP};
+use std::collections::VecDeque;
+
+pub struct Cfg {
+    // successor of every instruction, None when it jumps outside the program or is invalid
+    successors: Vec<Option<usize>>,
+    flipped: Vec<Option<usize>>, // successor when a jmp were a nop and vice versa
+    invalid: Vec<bool>,
+}
+
+fn target(pc: usize, offset: i32, len: usize) -> Option<usize> {
+    let target = pc as i64 + offset as i64;
+    if target < 0 || target > len as i64 {
+        None
+    } else {
+        Some(target as usize)
+    }
+}
+
+impl Cfg {
+    pub fn build(program: &[Instruction]) -> Self {
+        let len = program.len();
+        let mut successors = Vec::with_capacity(len);
+        let mut flipped = Vec::with_capacity(len);
+        let mut invalid = vec![false; len];
+        for (pc, ins) in program.iter().enumerate() {
+            let next = target(pc, 1, len);
+            let jump = target(pc, ins.arg, len);
+            match ins.op {
+                JMP => {
+                    successors.push(jump);
+                    flipped.push(next);
+                }
+                NOP => {
+                    successors.push(next);
+                    flipped.push(jump);
+                }
+                ACC => {
+                    successors.push(next);
+                    flipped.push(next);
+                }
+                _ => {
+                    successors.push(None);
+                    flipped.push(None);
+                    invalid[pc] = true;
+                }
+            }
+        }
+        Cfg {
+            successors,
+            flipped,
+            invalid,
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// For every node (including the end), whether it reaches the end of the program.
+    /// Found by walking the edges backwards from the end: linear in the program length.
+    pub fn reaching_end(&self) -> Vec<bool> {
+        let mut predecessors = vec![Vec::new(); self.end() + 1];
+        for (pc, succ) in self.successors.iter().enumerate() {
+            if let Some(succ) = succ {
+                predecessors[*succ].push(pc);
+            }
+        }
+        let mut reaches = vec![false; self.end() + 1];
+        reaches[self.end()] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(self.end());
+        while let Some(node) = queue.pop_front() {
+            for &pred in predecessors[node].iter() {
+                if !reaches[pred] {
+                    reaches[pred] = true;
+                    queue.push_back(pred);
+                }
+            }
+        }
+        reaches
+    }
+
+    /// Instructions executed from pc 0 until the program ends, loops or leaves the program.
+    pub fn execution_path(&self) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut seen = vec![false; self.end()];
+        let mut pc = 0;
+        while pc < self.end() && !seen[pc] {
+            seen[pc] = true;
+            path.push(pc);
+            match self.successors[pc] {
+                Some(next) => pc = next,
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// The single `jmp`/`nop` on the execution path that, once flipped, leads to the end.
+    /// The end can only be reached through instructions that already reach it, so one
+    /// reverse reachability pass replaces running the program once for every candidate.
+    pub fn find_corrupt_instruction(&self) -> Option<usize> {
+        let reaches = self.reaching_end();
+        self.execution_path().into_iter().find(|&pc| {
+            self.flipped[pc] != self.successors[pc]
+                && self.flipped[pc].is_some_and(|flipped| reaches[flipped])
+        })
+    }
+
+    /// Graphviz representation, instructions reaching the end are green, invalid ones red
+    /// and without edges, dashed edges show where a flipped `jmp`/`nop` would go.
+    pub fn to_dot(&self, program: &[Instruction], set: &InstructionSet) -> String {
+        let reaches = self.reaching_end();
+        let corrupt = self.find_corrupt_instruction();
+        let mut dot =
+            String::from("digraph program {\n    node [shape=box, fontname=monospace];\n");
+        for (pc, ins) in program.iter().enumerate() {
+            let (note, fill) = if self.invalid[pc] {
+                (" (invalid)", ", style=filled, fillcolor=salmon")
+            } else if reaches[pc] {
+                ("", ", style=filled, fillcolor=palegreen")
+            } else {
+                ("", "")
+            };
+            let mut attributes = format!("label=\"{}: {}{}\"{}", pc, set.format(ins), note, fill);
+            if corrupt == Some(pc) {
+                attributes.push_str(", color=red, penwidth=2");
+            }
+            dot.push_str(&format!("    n{} [{}];\n", pc, attributes));
+        }
+        dot.push_str(&format!(
+            "    n{} [label=\"end\", shape=doublecircle, style=filled, fillcolor=palegreen];\n",
+            self.end()
+        ));
+        for pc in 0..self.end() {
+            if let Some(succ) = self.successors[pc] {
+                dot.push_str(&format!("    n{} -> n{};\n", pc, succ));
+            }
+            if let Some(flipped) = self.flipped[pc] {
+                if self.flipped[pc] != self.successors[pc] {
+                    let color = if corrupt == Some(pc) { "red" } else { "gray" };
+                    dot.push_str(&format!(
+                        "    n{} -> n{} [style=dashed, color={}];\n",
+                        pc, flipped, color
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::read_program;
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn test_reaching_end() {
+        let cfg = Cfg::build(&read_program(EXAMPLE));
+        let reaching: Vec<usize> = cfg
+            .reaching_end()
+            .iter()
+            .enumerate()
+            .filter(|(_, &r)| r)
+            .map(|(pc, _)| pc)
+            .collect();
+        assert_eq!(reaching, vec![8, 9]);
+        assert_eq!(cfg.execution_path(), vec![0, 1, 2, 6, 7, 3, 4]);
+    }
+
+    #[test]
+    fn test_find_corrupt_instruction() {
+        let cfg = Cfg::build(&read_program(EXAMPLE));
+        assert_eq!(cfg.find_corrupt_instruction(), Some(7));
+
+        let cfg = Cfg::build(&read_program("jmp +0\nacc +1"));
+        assert_eq!(cfg.find_corrupt_instruction(), Some(0));
+
+        let cfg = Cfg::build(&read_program("jmp +1\njmp -1\njmp -2"));
+        assert_eq!(cfg.find_corrupt_instruction(), None);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let program = read_program("nop +1\njmp -1");
+        let dot = Cfg::build(&program).to_dot(&program, &InstructionSet::handheld());
+        assert!(dot.starts_with("digraph program {"));
+        assert!(dot.contains("    n0 [label=\"0: nop +1\"];\n"));
+        assert!(dot.contains("    n1 [label=\"1: jmp -1\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("    n0 -> n1;\n"));
+        assert!(dot.contains("    n1 -> n0;\n"));
+        assert!(dot.contains("    n1 -> n2 [style=dashed, color=red];\n"));
+
+        // the VM halts on an invalid instruction, so nothing comes after it
+        let program = read_program("nop +1\nxyz +1\nacc +1");
+        let cfg = Cfg::build(&program);
+        assert_eq!(cfg.execution_path(), vec![0, 1]);
+        assert_eq!(cfg.reaching_end(), vec![false, false, true, true]);
+        let dot = cfg.to_dot(&program, &InstructionSet::handheld());
+        assert!(dot
+            .contains("    n1 [label=\"1: ??? +1 (invalid)\", style=filled, fillcolor=salmon];\n"));
+        assert!(!dot.contains("    n1 ->"));
+    }
+}
@@ -0,0 +1,494 @@
+// Client for the puzzle site: downloads inputs (once) and submits answers.
+// The session token comes from $AOC_SESSION or the .aoc/session file,
+// the base url from $AOC_BASE_URL so everything can be pointed at a local server.
+
+use crate::answers::{read_known_answers, read_wrong_answers, store_answer, store_wrong_answer};
+use crate::scaffold::{DayFiles, YEAR};
+use std::fs::{create_dir_all, metadata, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_BASE_URL: &str = "https://adventofcode.com";
+pub const CONFIG_DIR: &str = ".aoc";
+const USER_AGENT: &str = "github.com/jonasvandervennet/advent-of-code-2020";
+// be nice to the servers, even when called in a loop
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Accepted,
+    Rejected { hint: Option<String> },
+    TooSoon { wait: Option<String> },
+    AlreadySolved,
+    Unknown(String),
+}
+
+/// What became of an answer: sent to the site, or judged from the answers file alone.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Sent(Verdict),
+    AlreadyAccepted,               // the same as the answer the site accepted before
+    Mismatch { accepted: String }, // differs from the answer the site accepted before
+    RejectedBefore,
+}
+
+/// Reads the outcome of a submission from the returned page.
+fn parse_verdict(page: &str) -> Verdict {
+    if page.contains("That's the right answer") {
+        Verdict::Accepted
+    } else if page.contains("That's not the right answer") {
+        let hint = if page.contains("your answer is too high") {
+            Some("too high".to_string())
+        } else if page.contains("your answer is too low") {
+            Some("too low".to_string())
+        } else {
+            None
+        };
+        Verdict::Rejected { hint }
+    } else if page.contains("You gave an answer too recently") {
+        // "You have 34s left to wait." or "You have 1m 5s left to wait."
+        let wait = page
+            .split("You have ")
+            .nth(1)
+            .and_then(|rest| rest.split(" left to wait").next())
+            .map(|wait| wait.to_string());
+        Verdict::TooSoon { wait }
+    } else if page.contains("You don't seem to be solving the right level") {
+        Verdict::AlreadySolved
+    } else {
+        // keep the bare text of the main article, if any
+        let text = page
+            .split("<article>")
+            .nth(1)
+            .and_then(|rest| rest.split("</article>").next())
+            .unwrap_or(page);
+        Verdict::Unknown(strip_tags(text).trim().to_string())
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub struct Client {
+    base_url: String,
+    session: String,
+    agent: ureq::Agent,
+    min_interval: Duration,
+    // time of the last request is kept on disk, so separate runs are limited too
+    state_file: PathBuf,
+}
+
+impl Client {
+    pub fn new(base_url: &str, session: &str, config_dir: &Path) -> Self {
+        Client {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            session: session.trim().to_string(),
+            agent: ureq::AgentBuilder::new().user_agent(USER_AGENT).build(),
+            min_interval: MIN_REQUEST_INTERVAL,
+            state_file: config_dir.join("last_request"),
+        }
+    }
+
+    /// Client configured from the environment, falling back to the files in `.aoc/`.
+    pub fn from_env(base_url: Option<&str>) -> Result<Self, String> {
+        let config_dir = Path::new(CONFIG_DIR);
+        let session = match std::env::var("AOC_SESSION") {
+            Ok(session) => session,
+            Err(_) => read_to_string(config_dir.join("session")).map_err(|_| {
+                format!(
+                    "No session token, set $AOC_SESSION or write it to {}/session",
+                    CONFIG_DIR
+                )
+            })?,
+        };
+        let base_url = match base_url {
+            Some(url) => url.to_string(),
+            None => std::env::var("AOC_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
+        };
+        Ok(Client::new(&base_url, &session, config_dir))
+    }
+
+    #[cfg(test)]
+    fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    fn wait_for_rate_limit(&self) {
+        let last = read_to_string(&self.state_file)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let next = last + self.min_interval.as_millis() as u64;
+        let now = now_millis();
+        if next > now {
+            sleep(Duration::from_millis(next - now));
+        }
+        if let Some(dir) = self.state_file.parent() {
+            let _ = create_dir_all(dir);
+        }
+        let _ = write(&self.state_file, now_millis().to_string());
+    }
+
+    fn cookie(&self) -> String {
+        format!("session={}", self.session)
+    }
+
+    fn request_error(e: ureq::Error) -> String {
+        match e {
+            ureq::Error::Status(400, _) => "Bad request, is the session token valid?".to_string(),
+            ureq::Error::Status(404, _) => "Puzzle not found, is it unlocked yet?".to_string(),
+            e => e.to_string(),
+        }
+    }
+
+    /// Downloads the input of a day to `path`, unless it is already there.
+    /// An empty file, like the one `new` creates, does not count as downloaded.
+    /// Returns whether anything was downloaded.
+    pub fn fetch_input(&self, year: usize, day: usize, path: &Path) -> Result<bool, String> {
+        if metadata(path).is_ok_and(|meta| meta.len() > 0) {
+            return Ok(false);
+        }
+        self.wait_for_rate_limit();
+        let url = format!("{}/{}/day/{}/input", self.base_url, year, day);
+        let input = self
+            .agent
+            .get(&url)
+            .set("Cookie", &self.cookie())
+            .call()
+            .map_err(Client::request_error)?
+            .into_string()
+            .map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        write(path, input).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(true)
+    }
+
+    /// Submits an answer and stores the verdict in the answers file.
+    /// Answers that are already known right or wrong are not sent, the outcome says why.
+    pub fn submit(
+        &self,
+        year: usize,
+        day: usize,
+        part: usize,
+        answer: &str,
+        answers_path: &Path,
+    ) -> Result<Outcome, String> {
+        let known = read_known_answers(answers_path);
+        let known = if part == 1 { known.0 } else { known.1 };
+        if !known.is_empty() {
+            return Ok(if known == answer {
+                Outcome::AlreadyAccepted
+            } else {
+                Outcome::Mismatch { accepted: known }
+            });
+        }
+        if read_wrong_answers(answers_path, part)
+            .iter()
+            .any(|a| a == answer)
+        {
+            return Ok(Outcome::RejectedBefore);
+        }
+
+        self.wait_for_rate_limit();
+        let url = format!("{}/{}/day/{}/answer", self.base_url, year, day);
+        let page = self
+            .agent
+            .post(&url)
+            .set("Cookie", &self.cookie())
+            .send_form(&[("level", &part.to_string()), ("answer", answer)])
+            .map_err(Client::request_error)?
+            .into_string()
+            .map_err(|e| e.to_string())?;
+
+        let verdict = parse_verdict(&page);
+        let stored = match &verdict {
+            Verdict::Accepted => store_answer(answers_path, part, answer),
+            Verdict::Rejected { hint } => {
+                store_wrong_answer(answers_path, part, answer, hint.as_deref())
+            }
+            _ => Ok(()),
+        };
+        stored.map_err(|e| format!("{}: {}", answers_path.display(), e))?;
+        Ok(Outcome::Sent(verdict))
+    }
+}
+
+pub fn fetch(client: &Client, day: usize) -> Result<(), String> {
+    let files = DayFiles::new(day);
+    if client.fetch_input(YEAR, day, Path::new(&files.input))? {
+        println!("Downloaded {}", files.input);
+    } else {
+        println!("{} is cached, not downloading again", files.input);
+    }
+    Ok(())
+}
+
+pub fn submit(client: &Client, day: usize, part: usize, answer: &str) -> Result<(), String> {
+    let files = DayFiles::new(day);
+    let verdict = match client.submit(YEAR, day, part, answer, Path::new(&files.answers))? {
+        Outcome::Sent(verdict) => verdict,
+        Outcome::AlreadyAccepted => {
+            println!(
+                "PART {}: {} was accepted before, not sent again",
+                part, answer
+            );
+            return Ok(());
+        }
+        Outcome::Mismatch { accepted } => {
+            println!(
+                "PART {}: {} differs from the accepted answer {}, not sent",
+                part, answer, accepted
+            );
+            return Ok(());
+        }
+        Outcome::RejectedBefore => {
+            println!(
+                "PART {}: {} was rejected before, not sent again",
+                part, answer
+            );
+            return Ok(());
+        }
+    };
+    match verdict {
+        Verdict::Accepted => println!("PART {}: {} is right!", part, answer),
+        Verdict::Rejected { hint: Some(hint) } => {
+            println!("PART {}: {} is wrong ({})", part, answer, hint)
+        }
+        Verdict::Rejected { hint: None } => println!("PART {}: {} is wrong", part, answer),
+        Verdict::TooSoon { wait: Some(wait) } => println!("Too soon, wait {} first", wait),
+        Verdict::TooSoon { wait: None } => println!("Too soon, wait a bit first"),
+        Verdict::AlreadySolved => println!("PART {} is already solved", part),
+        Verdict::Unknown(text) => println!("Unexpected response: {}", text),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_dir_all;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Instant;
+
+    // Minimal server answering every request with the page `respond` picks for its path.
+    // Returns the base url and a log of "METHOD path body" for every request.
+    fn mock_server(respond: fn(&str, &str) -> String) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let server_log = Arc::clone(&log);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                let mut cookie = String::new();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    let lower = header.to_lowercase();
+                    if lower.starts_with("content-length:") {
+                        content_length = header[15..].trim().parse().unwrap();
+                    } else if lower.starts_with("cookie:") {
+                        cookie = header[7..].trim().to_string();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body = String::from_utf8(body).unwrap();
+
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+                server_log
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {} {} {}", method, path, body, cookie));
+
+                let page = respond(&path, &body);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .unwrap();
+            }
+        });
+        (base_url, log)
+    }
+
+    fn site(path: &str, body: &str) -> String {
+        if path.ends_with("/input") {
+            return "1\n2\n3\n".to_string();
+        }
+        let page = if body.contains("answer=42") {
+            "That's the right answer!"
+        } else if body.contains("answer=1000") {
+            "That's not the right answer; your answer is too high."
+        } else {
+            "You gave an answer too recently. You have 34s left to wait."
+        };
+        format!("<main><article><p>{}</p></article></main>", page)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc-client-{}-{}", name, std::process::id()));
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        assert_eq!(parse_verdict(&site("", "answer=42")), Verdict::Accepted);
+        assert_eq!(
+            parse_verdict(&site("", "answer=1000")),
+            Verdict::Rejected {
+                hint: Some("too high".to_string())
+            }
+        );
+        assert_eq!(
+            parse_verdict(&site("", "answer=7")),
+            Verdict::TooSoon {
+                wait: Some("34s".to_string())
+            }
+        );
+        assert_eq!(
+            parse_verdict(
+                "<article><p>You don't seem to be solving the right level.</p></article>"
+            ),
+            Verdict::AlreadySolved
+        );
+        assert_eq!(
+            parse_verdict("<article><p>Something <em>else</em></p></article>"),
+            Verdict::Unknown("Something else".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fetch_input_is_cached() {
+        let (base_url, log) = mock_server(site);
+        let dir = temp_dir("fetch");
+        let client = Client::new(&base_url, "secret\n", &dir).with_min_interval(Duration::ZERO);
+        let input = dir.join("inputs/day1.txt");
+
+        assert_eq!(client.fetch_input(2020, 1, &input), Ok(true));
+        assert_eq!(read_to_string(&input).unwrap(), "1\n2\n3\n");
+        assert_eq!(client.fetch_input(2020, 1, &input), Ok(false));
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0], "GET /2020/day/1/input  session=secret");
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_input_into_empty_file() {
+        let (base_url, log) = mock_server(site);
+        let dir = temp_dir("fetch-empty");
+        let client = Client::new(&base_url, "secret\n", &dir).with_min_interval(Duration::ZERO);
+        let input = dir.join("inputs/day1.txt");
+        create_dir_all(input.parent().unwrap()).unwrap();
+        write(&input, "").unwrap();
+
+        assert_eq!(client.fetch_input(2020, 1, &input), Ok(true));
+        assert_eq!(read_to_string(&input).unwrap(), "1\n2\n3\n");
+        assert_eq!(log.lock().unwrap().len(), 1);
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_submit_stores_verdicts() {
+        let (base_url, log) = mock_server(site);
+        let dir = temp_dir("submit");
+        let client = Client::new(&base_url, "secret", &dir).with_min_interval(Duration::ZERO);
+        let answers = dir.join("day1_answers.txt");
+
+        let verdict = client.submit(2020, 1, 1, "1000", &answers).unwrap();
+        assert_eq!(
+            verdict,
+            Outcome::Sent(Verdict::Rejected {
+                hint: Some("too high".to_string())
+            })
+        );
+        // known wrong, not sent again
+        let verdict = client.submit(2020, 1, 1, "1000", &answers).unwrap();
+        assert_eq!(verdict, Outcome::RejectedBefore);
+        let verdict = client.submit(2020, 1, 1, "7", &answers).unwrap();
+        assert_eq!(
+            verdict,
+            Outcome::Sent(Verdict::TooSoon {
+                wait: Some("34s".to_string())
+            })
+        );
+        assert_eq!(
+            client.submit(2020, 1, 1, "42", &answers),
+            Ok(Outcome::Sent(Verdict::Accepted))
+        );
+        // known right, neither answer is sent
+        assert_eq!(
+            client.submit(2020, 1, 1, "42", &answers),
+            Ok(Outcome::AlreadyAccepted)
+        );
+        assert_eq!(
+            client.submit(2020, 1, 1, "43", &answers),
+            Ok(Outcome::Mismatch {
+                accepted: "42".to_string()
+            })
+        );
+        assert_eq!(read_known_answers(&answers).0, "42");
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(
+            log[0],
+            "POST /2020/day/1/answer level=1&answer=1000 session=secret"
+        );
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let (base_url, log) = mock_server(site);
+        let dir = temp_dir("rate");
+        let client =
+            Client::new(&base_url, "secret", &dir).with_min_interval(Duration::from_millis(300));
+
+        let start = Instant::now();
+        client.fetch_input(2020, 1, &dir.join("day1.txt")).unwrap();
+        client.fetch_input(2020, 2, &dir.join("day2.txt")).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert_eq!(log.lock().unwrap().len(), 2);
+        remove_dir_all(&dir).unwrap();
+    }
+}
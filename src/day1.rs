@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2, read_lines};
+use crate::util::{print_part_1, print_part_2, read_lines, start_part};
 use itertools::Itertools;
-use std::time::Instant;
 
 fn get_2_values_with_sum_2020(values: &Vec<usize>) -> (usize, usize) {
     for (i, val) in values.iter().enumerate() {
@@ -57,7 +56,7 @@ pub fn main() {
         }
     };
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "319531";
     let part_1: usize = {
         let values = get_2_values_with_sum_2020(&input);
@@ -67,7 +66,7 @@ pub fn main() {
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "244300320";
     let part_2: usize = get_3_values_with_sum_2020(&input);
     let duration = start.elapsed();
@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn patch_cables(input: &Vec<usize>) -> usize {
     let mut input = input.clone();
@@ -63,14 +62,14 @@ pub fn main() {
         .collect();
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2201";
     let part_1: usize = patch_cables(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "169255295254528";
     let part_2 = num_arrangements(&input);
     let duration = start.elapsed();
@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 #[derive(Copy, Clone, PartialEq)]
 enum GridType {
@@ -193,14 +192,14 @@ pub fn main() {
     let input = read_to_string("inputs/day11.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2247";
     let part_1: usize = iterate_until_stable(&mut Grid::new(&input, 1));
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2011";
     let part_2: usize = iterate_until_stable(&mut Grid::new(&input, 2));
     let duration = start.elapsed();
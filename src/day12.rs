@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn follow_path_1(input: &str) -> i64 {
     let mut north: i64 = 0;
@@ -89,14 +88,14 @@ pub fn main() {
     let input = read_to_string("inputs/day12.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "415";
     let part_1: i64 = follow_path_1(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "29401";
     let part_2: i64 = follow_path_2(&input);
     let duration = start.elapsed();
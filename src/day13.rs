@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn schedule_bus(input: &str) -> usize {
     let mut lines = input.lines();
@@ -76,14 +75,14 @@ pub fn main() {
     let input = read_to_string("inputs/day13.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2406";
     let part_1: usize = schedule_bus(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "225850756401039";
     let part_2: usize = win_contest(&input);
     let duration = start.elapsed();
@@ -1,8 +1,7 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn sum_memory(input: &str) -> usize {
     let mut memory: HashMap<usize, usize> = HashMap::new();
@@ -105,14 +104,14 @@ pub fn main() {
     let input = read_to_string("inputs/day14.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "13105044880745";
     let part_1: usize = sum_memory(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "3505392154485";
     let part_2: usize = sum_memory_2(&input);
     let duration = start.elapsed();
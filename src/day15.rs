@@ -1,5 +1,4 @@
-use crate::util::{print_part_1, print_part_2};
-use std::time::Instant;
+use crate::util::{print_part_1, print_part_2, start_part};
 
 fn play_repeat(input: &str, goal_iterations: usize) -> usize {
     let mut num_spoken = 0;
@@ -38,14 +37,14 @@ pub fn main() {
     let input = "0,13,16,17,1,10,6";
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "276";
     let part_1: usize = play_repeat(&input, 2020);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "31916";
     let part_2: usize = play_repeat(&input, 30000000);
     let duration = start.elapsed();
@@ -1,167 +1,457 @@
-use crate::util::{print_part_1, print_part_2};
-use std::cmp;
-use std::collections::HashMap;
+use crate::intervals::IntervalSet;
+use crate::matching::Matching;
+use crate::util::{print_part_1, print_part_2, start_part};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 #[derive(Debug)]
 struct Field {
-    bounds: Vec<(usize, usize)>,
+    name: String,
+    ranges: Vec<(usize, usize)>, // as written in the notes
+    valid: IntervalSet,          // the same ranges, merged
 }
 
-impl Field {
-    fn possible(&self, value: usize) -> bool {
-        for (lower, upper) in &self.bounds {
-            if value >= *lower && value <= *upper {
-                return true;
+fn parse_ticket(line: &str) -> Result<Vec<usize>, String> {
+    line.split(',')
+        .map(|n| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid ticket value '{}'", n))
+        })
+        .collect()
+}
+
+/// Whether a field name matches a selector like `departure*`, where `*` stands for any text.
+fn matches_selector(selector: &str, name: &str) -> bool {
+    let mut parts = selector.split('*');
+    let first = parts.next().unwrap();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        None => rest.is_empty(), // no '*' at all
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
             }
+            rest.ends_with(last)
         }
-        false
     }
 }
 
-fn check_invalid_tickets(input: &str) -> usize {
-    let mut sections = input.split("\r\n\r\n");
-
-    let fields_s = sections.next().expect("No field ranges provided..");
-    let fields = fields_s
-        .lines()
-        .map(|line| {
-            let mut bounds = Vec::new();
-            for bound in line.split(": ").collect::<Vec<&str>>()[1].split(" or ") {
-                let split = bound.split("-").collect::<Vec<&str>>();
-                bounds.push((
-                    split[0].parse::<usize>().unwrap(),
-                    split[1].parse::<usize>().unwrap(),
+/// The rules for the fields, your ticket and the nearby tickets.
+struct Notes {
+    fields: Vec<Field>,
+    your_ticket: Vec<usize>,
+    nearby: Vec<Vec<usize>>,
+    any_field: IntervalSet, // the values that are valid for at least one field
+}
+
+impl Notes {
+    fn parse(input: &str) -> Result<Self, String> {
+        let input = input.replace("\r\n", "\n");
+        let mut sections = input.split("\n\n");
+
+        let fields = sections
+            .next()
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let mut split = line.splitn(2, ": ");
+                let (name, rule) = match (split.next(), split.next()) {
+                    (Some(name), Some(rule)) => (name, rule),
+                    _ => return Err(format!("invalid field '{}'", line)),
+                };
+                let bounds = rule
+                    .split(" or ")
+                    .map(|bound| {
+                        let mut split = bound.split('-').map(|n| n.trim().parse::<usize>());
+                        match (split.next(), split.next(), split.next()) {
+                            (Some(Ok(lower)), Some(Ok(upper)), None) if lower <= upper => {
+                                Ok((lower, upper))
+                            }
+                            _ => Err(format!("invalid range '{}'", bound)),
+                        }
+                    })
+                    .collect::<Result<Vec<(usize, usize)>, String>>()?;
+                Ok(Field {
+                    name: name.to_string(),
+                    valid: bounds.iter().copied().collect(),
+                    ranges: bounds,
+                })
+            })
+            .collect::<Result<Vec<Field>, String>>()?;
+        let mut names = HashSet::new();
+        if let Some(field) = fields.iter().find(|field| !names.insert(&field.name)) {
+            return Err(format!("field '{}' appears twice", field.name));
+        }
+
+        let your_ticket = sections
+            .next()
+            .and_then(|section| section.lines().nth(1))
+            .ok_or("no personal ticket provided")?;
+        let your_ticket = parse_ticket(your_ticket)?;
+
+        let nearby = sections
+            .next()
+            .ok_or("no other tickets provided")?
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(parse_ticket)
+            .collect::<Result<Vec<Vec<usize>>, String>>()?;
+
+        for ticket in std::iter::once(&your_ticket).chain(nearby.iter()) {
+            if ticket.len() != fields.len() {
+                return Err(format!(
+                    "a ticket has {} values, there are {} fields",
+                    ticket.len(),
+                    fields.len()
                 ));
             }
-            Field { bounds: bounds }
+        }
+        let any_field = fields
+            .iter()
+            .fold(IntervalSet::default(), |any, field| any.union(&field.valid));
+        Ok(Notes {
+            fields,
+            your_ticket,
+            nearby,
+            any_field,
         })
-        .collect::<Vec<Field>>();
+    }
 
-    // unused atm
-    sections.next().expect("No personal ticket provided..");
+    fn is_valid(&self, value: usize) -> bool {
+        self.any_field.contains(value)
+    }
+}
 
-    let tickets_s = sections.next().expect("No other tickets provided..");
-    tickets_s.lines().skip(1).fold(0, |acc, line| {
-        for n in line.split(",") {
-            let n = n.parse::<usize>().unwrap();
-            if !fields.iter().map(|field| field.possible(n)).any(|x| x) {
-                return acc + n;
-            }
+/// A range of a field that a value is not in.
+#[derive(Serialize, Debug, PartialEq)]
+struct Miss {
+    field: String,
+    lower: usize,
+    upper: usize,
+    distance: usize, // how far the value is below or above the range
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct InvalidValue {
+    column: usize,
+    value: usize,
+    closest: Vec<Miss>, // all ranges at the smallest distance
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TicketReport {
+    ticket: usize, // counting the nearby tickets from 0, like the columns
+    values: Vec<usize>,
+    invalid: Vec<InvalidValue>,
+}
+
+/// Every nearby ticket with a value that fits no field.
+#[derive(Serialize, Debug, PartialEq)]
+struct ScanReport {
+    tickets: usize,
+    error_rate: usize, // the sum of all invalid values
+    invalid_tickets: Vec<TicketReport>,
+}
+
+fn closest_ranges(notes: &Notes, value: usize) -> Vec<Miss> {
+    let misses: Vec<Miss> = notes
+        .fields
+        .iter()
+        .flat_map(|field| {
+            field.ranges.iter().map(move |&(lower, upper)| Miss {
+                field: field.name.clone(),
+                lower,
+                upper,
+                distance: if value < lower {
+                    lower - value
+                } else {
+                    value.saturating_sub(upper)
+                },
+            })
+        })
+        .collect();
+    let closest = misses.iter().map(|miss| miss.distance).min();
+    misses
+        .into_iter()
+        .filter(|miss| Some(miss.distance) == closest)
+        .collect()
+}
+
+fn scan_tickets(notes: &Notes) -> ScanReport {
+    let invalid_tickets: Vec<TicketReport> = notes
+        .nearby
+        .iter()
+        .enumerate()
+        .map(|(i, ticket)| TicketReport {
+            ticket: i,
+            values: ticket.clone(),
+            invalid: ticket
+                .iter()
+                .enumerate()
+                .filter(|(_, &value)| !notes.is_valid(value))
+                .map(|(column, &value)| InvalidValue {
+                    column,
+                    value,
+                    closest: closest_ranges(notes, value),
+                })
+                .collect(),
+        })
+        .filter(|report| !report.invalid.is_empty())
+        .collect();
+    ScanReport {
+        tickets: notes.nearby.len(),
+        error_rate: invalid_tickets
+            .iter()
+            .flat_map(|report| report.invalid.iter().map(|invalid| invalid.value))
+            .sum(),
+        invalid_tickets,
+    }
+}
+
+fn check_invalid_tickets(input: &str) -> usize {
+    let notes = Notes::parse(input).expect("Invalid notes..");
+    scan_tickets(&notes).error_rate
+}
+
+/// Prints every value of the nearby tickets that fits no field, with the ranges it is closest to.
+pub fn print_scan(input: &str, json: bool) {
+    let report = match Notes::parse(input) {
+        Ok(notes) => scan_tickets(&notes),
+        Err(e) => {
+            eprintln!("ERR: {}", e);
+            return;
         }
-        acc
-    })
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
+    for ticket in report.invalid_tickets.iter() {
+        println!(
+            "ticket {}: {}",
+            ticket.ticket,
+            ticket.values.iter().join(",")
+        );
+        for invalid in ticket.invalid.iter() {
+            let closest = invalid
+                .closest
+                .iter()
+                .map(|miss| {
+                    let side = if invalid.value < miss.lower {
+                        "below"
+                    } else {
+                        "above"
+                    };
+                    format!(
+                        "{} {} {} ({}-{})",
+                        miss.distance, side, miss.field, miss.lower, miss.upper
+                    )
+                })
+                .join(" or ");
+            println!(
+                "  column {}: {} is {}",
+                invalid.column, invalid.value, closest
+            );
+        }
+    }
+    println!(
+        "{} of {} nearby tickets are invalid, error rate {}",
+        report.invalid_tickets.len(),
+        report.tickets,
+        report.error_rate
+    );
 }
 
-fn determine_field_order(input: &str) -> usize {
-    let mut sections = input.split("\r\n\r\n");
+/// Which column every field is in, and the other columns it could be in as well.
+struct Assignment {
+    columns: Vec<usize>,
+    alternatives: Vec<Vec<usize>>,
+}
 
-    let fields_s = sections.next().expect("No field ranges provided..");
-    let fields = fields_s
-        .lines()
-        .map(|line| {
-            let mut bounds = Vec::new();
-            for bound in line.split(": ").collect::<Vec<&str>>()[1].split(" or ") {
-                let split = bound.split("-").collect::<Vec<&str>>();
-                bounds.push((
-                    split[0].parse::<usize>().unwrap(),
-                    split[1].parse::<usize>().unwrap(),
-                ));
-            }
-            Field { bounds: bounds }
+impl Assignment {
+    fn is_unique(&self) -> bool {
+        self.alternatives.iter().all(Vec::is_empty)
+    }
+}
+
+/// Matches every field to a column where it fits all valid tickets.
+fn assign_fields(notes: &Notes) -> Result<Assignment, String> {
+    let valid_tickets: Vec<&Vec<usize>> = notes
+        .nearby
+        .iter()
+        .filter(|ticket| ticket.iter().all(|&n| notes.is_valid(n)))
+        .collect();
+    // all values in a column, a field fits the column when it contains them all
+    let columns: Vec<IntervalSet> = (0..notes.fields.len())
+        .map(|column| {
+            valid_tickets
+                .iter()
+                .map(|ticket| (ticket[column], ticket[column]))
+                .collect()
         })
-        .collect::<Vec<Field>>();
-
-    // unused atm
-    let your_ticket_s = sections.next().expect("No personal ticket provided..");
-    let your_ticket = your_ticket_s
-        .lines()
-        .nth(1)
-        .unwrap()
-        .split(",")
-        .map(|n| n.parse::<usize>().unwrap())
-        .collect::<Vec<usize>>();
-
-    let tickets_s = sections.next().expect("No other tickets provided..");
-    let valid_tickets = tickets_s
-        .lines()
-        .skip(1)
-        .map(|line| {
-            line.split(",")
-                .map(|n| n.parse::<usize>().unwrap())
-                .collect::<Vec<usize>>()
+        .collect();
+    let candidates: Vec<Vec<usize>> = notes
+        .fields
+        .iter()
+        .map(|field| {
+            (0..notes.fields.len())
+                .filter(|&column| field.valid.intersection(&columns[column]) == columns[column])
+                .collect()
         })
-        .filter(|numbers| {
-            for n in numbers.iter() {
-                if !fields.iter().map(|field| field.possible(*n)).any(|x| x) {
-                    return false;
-                }
-            }
-            true
+        .collect();
+
+    let matching = Matching::maximum(&candidates, notes.fields.len());
+    if matching.size() < notes.fields.len() {
+        let unassigned = (0..notes.fields.len())
+            .filter(|&field| matching.partner(field).is_none())
+            .map(|field| format!("'{}'", notes.fields[field].name))
+            .join(", ");
+        return Err(format!(
+            "no column is left for {}, at most {} of {} fields can be placed",
+            unassigned,
+            matching.size(),
+            notes.fields.len()
+        ));
+    }
+    Ok(Assignment {
+        columns: (0..notes.fields.len())
+            .map(|field| matching.partner(field).unwrap())
+            .collect(),
+        alternatives: (0..notes.fields.len())
+            .map(|field| matching.alternatives(&candidates, field))
+            .collect(),
+    })
+}
+
+/// The fields of `ticket` that match `selector`, by name.
+fn decode_ticket<'a>(
+    notes: &'a Notes,
+    assignment: &Assignment,
+    ticket: &[usize],
+    selector: &str,
+) -> Result<BTreeMap<&'a str, usize>, String> {
+    let selected: Vec<usize> = (0..notes.fields.len())
+        .filter(|&field| matches_selector(selector, &notes.fields[field].name))
+        .collect();
+    if selected.is_empty() {
+        return Err(format!("no field matches '{}'", selector));
+    }
+    // the other fields may be uncertain, as long as the selected ones are not
+    if let Some(&field) = selected
+        .iter()
+        .find(|&&field| !assignment.alternatives[field].is_empty())
+    {
+        return Err(format!(
+            "'{}' could be in column {} or {}",
+            notes.fields[field].name,
+            assignment.columns[field],
+            assignment.alternatives[field].iter().join(" or ")
+        ));
+    }
+    Ok(selected
+        .into_iter()
+        .map(|field| {
+            (
+                notes.fields[field].name.as_str(),
+                ticket[assignment.columns[field]],
+            )
         })
-        .collect::<Vec<Vec<usize>>>();
-
-    let mut matching_fields = vec![usize::MAX; fields.len()];
-    loop {
-        let mut potential_allocations: HashMap<usize, Vec<usize>> = HashMap::new();
-        for (i, field) in fields.iter().enumerate() {
-            if matching_fields[i] != usize::MAX {
-                continue;
-            }
-            for j in 0..fields.len() {
-                let mut valid = true;
-                for ticket in valid_tickets.iter() {
-                    if !field.possible(ticket[j]) {
-                        valid = false;
-                        break;
-                    }
-                }
-                if valid && !matching_fields.contains(&j) {
-                    if potential_allocations.contains_key(&i) {
-                        potential_allocations.get_mut(&i).unwrap().push(j);
-                    } else {
-                        potential_allocations.insert(i, vec![j]);
-                    }
-                }
+        .collect())
+}
+
+fn determine_field_order(input: &str, selector: &str) -> Result<usize, String> {
+    let notes = Notes::parse(input)?;
+    let assignment = assign_fields(&notes)?;
+    let decoded = decode_ticket(&notes, &assignment, &notes.your_ticket, selector)?;
+    Ok(decoded.values().product())
+}
+
+/// Prints the fields of your ticket that match `selector`, or the product of their values.
+pub fn print_ticket(input: &str, selector: &str, product: bool) {
+    let decoded = Notes::parse(input).and_then(|notes| {
+        let assignment = assign_fields(&notes)?;
+        decode_ticket(&notes, &assignment, &notes.your_ticket, selector).map(|decoded| {
+            decoded
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<Vec<(String, usize)>>()
+        })
+    });
+    match decoded {
+        Ok(decoded) if product => {
+            println!(
+                "{}",
+                decoded.iter().map(|(_, value)| value).product::<usize>()
+            )
+        }
+        Ok(decoded) => {
+            for (name, value) in decoded {
+                println!("{}: {}", name, value);
             }
         }
+        Err(e) => eprintln!("ERR: {}", e),
+    }
+}
 
-        for (field_nr, options) in potential_allocations {
-            if options.len() == 1 {
-                matching_fields[field_nr] = options[0];
-            }
+/// Prints the column of every field, and which fields could be in other columns too.
+pub fn print_assignment(input: &str) {
+    let notes = Notes::parse(input);
+    let (notes, assignment) = match notes.and_then(|notes| {
+        let assignment = assign_fields(&notes)?;
+        Ok((notes, assignment))
+    }) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("ERR: {}", e);
+            return;
         }
-        if !matching_fields.contains(&usize::MAX) {
-            break;
+    };
+    for (field, column) in assignment.columns.iter().enumerate() {
+        let name = &notes.fields[field].name;
+        let alternatives = &assignment.alternatives[field];
+        if alternatives.is_empty() {
+            println!("{}: column {}", name, column);
+        } else {
+            println!(
+                "{}: column {}, or {}",
+                name,
+                column,
+                alternatives.iter().join(" or ")
+            );
         }
     }
-
-    let mut reordered_ticket = vec![0; fields.len()];
-    for (new_i, old_i) in matching_fields.iter().enumerate() {
-        reordered_ticket[new_i] = your_ticket[*old_i];
+    if assignment.is_unique() {
+        println!("the assignment is unique");
+    } else {
+        println!("the assignment is ambiguous");
     }
-
-    // min check is for the test with only 3 fields
-    reordered_ticket[..cmp::min(6, fields.len())]
-        .iter()
-        .product()
 }
 
 pub fn main() {
     let input = read_to_string("inputs/day16.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "26869";
     let part_1: usize = check_invalid_tickets(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "855275529001";
-    let part_2: usize = determine_field_order(&input);
+    let part_2: usize = determine_field_order(&input, "departure*").expect("Invalid notes..");
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), &known_answer, duration);
 }
@@ -180,7 +470,124 @@ mod tests {
     #[test]
     fn test_example_2() {
         let input: &str = "class: 0-1 or 4-19\nrow: 0-5 or 8-19\nseat: 0-13 or 16-19\r\n\r\nyour ticket:\n11,12,13\r\n\r\nnearby tickets:\n3,9,18\n15,1,5\n55,2,20\n5,14,9";
-        let answer: usize = determine_field_order(&input);
+        let answer: usize = determine_field_order(input, "*").unwrap();
         assert_eq!(answer, 11 * 12 * 13);
+        let answer: usize = determine_field_order(input, "row").unwrap();
+        assert_eq!(answer, 11);
+    }
+
+    #[test]
+    fn test_scan() {
+        let notes = Notes::parse("class: 1-3 or 5-7\nrow: 6-11 or 33-44\nseat: 13-40 or 45-50\n\nyour ticket:\n7,1,14\n\nnearby tickets:\n7,3,47\n40,4,50\n55,2,20\n38,6,12\n4,0,12").unwrap();
+        let report = scan_tickets(&notes);
+        assert_eq!((report.tickets, report.error_rate), (5, 71 + 4 + 12));
+        let found: Vec<(usize, Vec<(usize, usize)>)> = report
+            .invalid_tickets
+            .iter()
+            .map(|ticket| {
+                (
+                    ticket.ticket,
+                    ticket
+                        .invalid
+                        .iter()
+                        .map(|invalid| (invalid.column, invalid.value))
+                        .collect(),
+                )
+            })
+            .collect();
+        // every invalid value of a ticket, not only the first
+        assert_eq!(
+            found,
+            vec![
+                (1, vec![(1, 4)]),
+                (2, vec![(0, 55)]),
+                (3, vec![(2, 12)]),
+                (4, vec![(0, 4), (1, 0), (2, 12)])
+            ]
+        );
+        // 4 is 1 above 1-3 and 1 below 5-7 of class
+        let closest = &report.invalid_tickets[0].invalid[0].closest;
+        assert_eq!(
+            closest
+                .iter()
+                .map(|miss| (miss.field.as_str(), miss.lower, miss.distance))
+                .collect::<Vec<_>>(),
+            vec![("class", 1, 1), ("class", 5, 1)]
+        );
+        let json = serde_json::to_value(&report.invalid_tickets[1]).unwrap();
+        assert_eq!(
+            json["invalid"][0]["closest"][0],
+            serde_json::json!({"field": "seat", "lower": 45, "upper": 50, "distance": 5})
+        );
+
+        // the ranges of the notes, even where they overlap
+        let notes = Notes::parse(
+            "a: 1-5 or 3-8
+
+your ticket:
+1
+
+nearby tickets:
+10",
+        )
+        .unwrap();
+        let report = scan_tickets(&notes);
+        let miss = &report.invalid_tickets[0].invalid[0].closest;
+        assert_eq!(
+            miss.iter()
+                .map(|miss| (miss.lower, miss.upper, miss.distance))
+                .collect::<Vec<_>>(),
+            vec![(3, 8, 2)]
+        );
+    }
+
+    #[test]
+    fn test_selector() {
+        assert!(matches_selector("departure*", "departure location"));
+        assert!(!matches_selector("departure*", "arrival location"));
+        assert!(matches_selector("*location", "arrival location"));
+        assert!(matches_selector("a*l*n", "arrival location"));
+        assert!(!matches_selector("a*l*x", "arrival location"));
+        assert!(matches_selector("*", ""));
+        assert!(matches_selector("row", "row"));
+        assert!(!matches_selector("row", "rows"));
+        // the parts may not overlap
+        assert!(!matches_selector("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_assignment() {
+        let notes = |tickets: &str| {
+            Notes::parse(&format!(
+                "a: 1-5\nb: 1-10\nc: 1-20\n\nyour ticket:\n1,2,3\n\nnearby tickets:\n{}",
+                tickets
+            ))
+            .unwrap()
+        };
+        // c is the only field for 15, then b for 8 and a for the last column
+        let assignment = assign_fields(&notes("8,1,15\n3,2,1")).unwrap();
+        assert_eq!(assignment.columns, vec![1, 0, 2]);
+        assert!(assignment.is_unique());
+
+        // a and b both fit the first two columns
+        let assignment = assign_fields(&notes("2,1,15")).unwrap();
+        assert_eq!(assignment.columns[2], 2);
+        assert_eq!(assignment.alternatives[2], Vec::<usize>::new());
+        assert_eq!(assignment.alternatives[0], vec![1 - assignment.columns[0]]);
+        assert!(!assignment.is_unique());
+
+        // a and b only fit the last column
+        assert_eq!(
+            assign_fields(&notes("11,11,1")).map(|_| ()),
+            Err("no column is left for 'b', at most 2 of 3 fields can be placed".to_string())
+        );
+        assert_eq!(
+            Notes::parse("a: 1-5\nb: 1-5\n\nyour ticket:\n1,2,3\n\nnearby tickets:\n").map(|_| ()),
+            Err("a ticket has 3 values, there are 2 fields".to_string())
+        );
+        assert_eq!(
+            Notes::parse("a: 5-1\n\nyour ticket:\n1\n\nnearby tickets:\n").map(|_| ()),
+            Err("invalid range '5-1'".to_string())
+        );
     }
 }
@@ -1,8 +1,7 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn booting_sequence(input: &str, dim: usize) -> usize {
     let mut actives: HashSet<Vec<i64>> = HashSet::new();
@@ -63,7 +62,7 @@ pub fn main() {
     let input = read_to_string("inputs/day17.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "247";
     // let part_1: usize = booting_sequence_3d(&input);
     let part_1: usize = booting_sequence(&input, 3);
@@ -71,7 +70,7 @@ pub fn main() {
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "1392";
     let part_2: usize = booting_sequence(&input, 4);
     let duration = start.elapsed();
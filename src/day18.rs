@@ -1,101 +1,433 @@
-use crate::util::{print_part_1, print_part_2};
-use std::collections::VecDeque;
+use crate::util::{print_part_1, print_part_2, start_part};
+use std::convert::TryFrom;
 use std::fs::read_to_string;
-use std::time::Instant;
 
-#[derive(Clone, Copy)]
-struct State {
-    lhs: usize,
-    op: char,
+// Expressions are tokenized and parsed into an AST by a Pratt parser.
+// The puzzle only uses single digits, `+`, `*` and parentheses, the parser also handles
+// multi-digit numbers, `-`, `/`, `^` (right associative) and unary minus.
 
-    initialised: bool,
-    parantheses_state: bool, // reason for push
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    Op(BinOp),
+    LParen,
+    RParen,
 }
 
-impl State {
-    fn new(by_parentheses: bool) -> Self {
-        State {
-            initialised: false,
-            parantheses_state: by_parentheses,
-            lhs: 0,
-            op: '+',
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+impl BinOp {
+    const ALL: [BinOp; 5] = [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Pow];
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        BinOp::ALL.iter().copied().find(|op| op.symbol() == symbol)
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Pow => "^",
         }
     }
-    fn do_op(&mut self, rhs: usize) {
-        if !self.initialised {
-            self.initialised = true;
-            self.lhs = rhs;
-            return;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(i64),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+// (position, token) pairs, positions are used in error messages
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '+' => Token::Op(BinOp::Add),
+            '-' => Token::Op(BinOp::Sub),
+            '*' => Token::Op(BinOp::Mul),
+            '/' => Token::Op(BinOp::Div),
+            '^' => Token::Op(BinOp::Pow),
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '0'..='9' => {
+                let mut end = pos + 1;
+                while let Some((i, '0'..='9')) = chars.peek() {
+                    end = i + 1;
+                    chars.next();
+                }
+                let number = &expr[pos..end];
+                Token::Num(
+                    number
+                        .parse()
+                        .map_err(|_| format!("number {} at {} is too large", number, pos))?,
+                )
+            }
+            _ => return Err(format!("unexpected character '{}' at {}", c, pos)),
+        };
+        tokens.push((pos, token));
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+/// Precedence and associativity of the binary operators, described by a table like
+/// `"+ > *"` or `"* > +, right-assoc ^"`: a chain from the tightest to the loosest binding
+/// level, `=` puts operators on the same level. Unlisted `-` and `/` share the level of
+/// `+` and `*`, other unlisted operators bind tighter than the listed ones.
+/// Operators are left associative unless listed after `right-assoc`.
+#[derive(Debug, PartialEq)]
+pub struct Precedence {
+    levels: [(u8, Assoc); 5], // by `BinOp as usize`, higher levels bind tighter
+}
+
+// the two parts of the puzzle
+pub const PART_1: &str = "+ = *, right-assoc ^";
+pub const PART_2: &str = "+ > *, right-assoc ^";
+
+impl Precedence {
+    pub fn parse(table: &str) -> Result<Self, String> {
+        let mut ranked: Vec<Vec<BinOp>> = Vec::new(); // tightest first
+        let mut right_assoc = Vec::new();
+        let mut listed = Vec::new();
+        let op = |symbol: &str| {
+            BinOp::from_symbol(symbol).ok_or_else(|| format!("unknown operator '{}'", symbol))
+        };
+
+        for clause in table.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            if let Some(ops) = clause.strip_prefix("right-assoc") {
+                for symbol in ops.split_whitespace() {
+                    right_assoc.push(op(symbol)?);
+                }
+            } else if let Some(ops) = clause.strip_prefix("left-assoc") {
+                // the default, only checked
+                for symbol in ops.split_whitespace() {
+                    op(symbol)?;
+                }
+            } else if !ranked.is_empty() {
+                return Err(format!("more than one precedence chain: '{}'", clause));
+            } else {
+                for level in clause.split('>') {
+                    let mut ops = Vec::new();
+                    for symbol in level.split('=').map(str::trim) {
+                        let op = op(symbol)?;
+                        if listed.contains(&op) {
+                            return Err(format!("operator '{}' is ranked twice", symbol));
+                        }
+                        listed.push(op);
+                        ops.push(op);
+                    }
+                    ranked.push(ops);
+                }
+            }
         }
-        match self.op {
-            '+' => {
-                self.lhs += rhs;
+
+        // unlisted operators: `-` and `/` join their counterpart, the rest goes on top
+        let mut top = Vec::new();
+        for op in BinOp::ALL.iter().copied().filter(|op| !listed.contains(op)) {
+            let partner = match op {
+                BinOp::Sub => Some(BinOp::Add),
+                BinOp::Div => Some(BinOp::Mul),
+                _ => None,
+            };
+            match partner.and_then(|partner| ranked.iter_mut().find(|l| l.contains(&partner))) {
+                Some(level) => level.push(op),
+                None => top.push(op),
             }
-            '*' => {
-                self.lhs *= rhs;
+        }
+        if !top.is_empty() {
+            ranked.insert(0, top);
+        }
+
+        let mut levels = [(0, Assoc::Left); 5];
+        for (i, ops) in ranked.iter().rev().enumerate() {
+            let assoc = |op: &BinOp| {
+                if right_assoc.contains(op) {
+                    Assoc::Right
+                } else {
+                    Assoc::Left
+                }
+            };
+            if ops.iter().any(|op| assoc(op) != assoc(&ops[0])) {
+                return Err("operators on the same level need the same associativity".to_string());
             }
-            _ => {
-                panic!("What is happening?");
+            for op in ops {
+                levels[*op as usize] = (i as u8 + 1, assoc(op));
             }
         }
+        Ok(Precedence { levels })
     }
 
-    fn value(&self) -> usize {
-        self.lhs
+    /// The precedence of part 1 or 2 of the puzzle.
+    pub fn part(part: usize) -> Self {
+        Precedence::parse(if part == 1 { PART_1 } else { PART_2 }).unwrap()
     }
+
+    /// (left, right) binding power, a higher left than right power makes it right associative.
+    fn infix_binding_power(&self, op: BinOp) -> (u8, u8) {
+        match self.levels[op as usize] {
+            (level, Assoc::Left) => (2 * level, 2 * level + 1),
+            (level, Assoc::Right) => (2 * level + 1, 2 * level),
+        }
+    }
+
+    // unary minus binds as tight as `^`, so -2^2 is -(2^2) but -2*3 is (-2)*3
+    fn prefix_binding_power(&self) -> u8 {
+        2 * self.levels[BinOp::Pow as usize].0
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize, // index of the next token
+    len: usize, // length of the expression, position of the end
+    precedence: &'a Precedence,
 }
 
-fn evaluate_expression(expr: &str, part: usize) -> usize {
-    let mut states: VecDeque<State> = VecDeque::new();
-    let mut curr_state = State::new(false);
+impl Parser<'_> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).map(|(_, token)| *token)
+    }
 
-    // put everything between a set of parentheses
-    let expr = format!("({})", expr);
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.len, |(pos, _)| *pos)
+    }
 
-    for c in expr.chars() {
-        match c {
-            ' ' => {
-                continue;
+    fn unexpected(&self) -> String {
+        match self.peek() {
+            Some(_) => format!("unexpected token at {}", self.position()),
+            None => "unexpected end of expression".to_string(),
+        }
+    }
+
+    fn parse_expr(&mut self, min_bp: u8) -> Result<Expr, String> {
+        let mut lhs = match self.peek() {
+            Some(Token::Num(n)) => {
+                self.pos += 1;
+                Expr::Num(n)
             }
-            '+' => {
-                curr_state.op = c;
+            Some(Token::Op(BinOp::Sub)) => {
+                self.pos += 1;
+                Expr::Neg(Box::new(
+                    self.parse_expr(self.precedence.prefix_binding_power())?,
+                ))
             }
-            '*' => {
-                curr_state.op = c;
-                if part == 2 {
-                    // addition has precedence over muiltiplication
-                    // so hold off on performing the mult for now
-                    states.push_back(curr_state);
-                    curr_state = State::new(false);
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let inner = self.parse_expr(0)?;
+                if self.peek() != Some(Token::RParen) {
+                    return Err(format!("expected ')' at {}", self.position()));
                 }
+                self.pos += 1;
+                inner
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        while let Some(Token::Op(op)) = self.peek() {
+            let (left_bp, right_bp) = self.precedence.infix_binding_power(op);
+            if left_bp < min_bp {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_expr(right_bp)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+}
+
+pub fn parse(expr: &str, precedence: &Precedence) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        len: expr.len(),
+        precedence,
+    };
+    let ast = parser.parse_expr(0)?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected());
+    }
+    Ok(ast)
+}
+
+impl Expr {
+    /// Integer arithmetic: division truncates, overflow and division by zero are errors.
+    pub fn eval(&self) -> Result<i64, String> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Neg(expr) => expr
+                .eval()?
+                .checked_neg()
+                .ok_or_else(|| "overflow".to_string()),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval()?, rhs.eval()?);
+                let result = match op {
+                    BinOp::Add => lhs.checked_add(rhs),
+                    BinOp::Sub => lhs.checked_sub(rhs),
+                    BinOp::Mul => lhs.checked_mul(rhs),
+                    BinOp::Div if rhs == 0 => return Err("division by zero".to_string()),
+                    BinOp::Div => lhs.checked_div(rhs),
+                    BinOp::Pow if rhs < 0 => {
+                        return Err(format!("negative exponent in {}^{}", lhs, rhs))
+                    }
+                    BinOp::Pow => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)),
+                };
+                result.ok_or_else(|| "overflow".to_string())
             }
-            '(' => {
-                states.push_back(curr_state);
-                curr_state = State::new(true);
-            }
-            ')' => loop {
-                // reduce for all values inside the same parentheses
-                let rhs = curr_state.value();
-                let parentheses_state = curr_state.parantheses_state;
-                curr_state = states.pop_back().unwrap();
-                curr_state.do_op(rhs);
-                if parentheses_state {
-                    break;
+        }
+    }
+
+    /// Every operation between parentheses: how the evaluator groups the expression.
+    pub fn parenthesize(&self) -> String {
+        match self {
+            Expr::Num(n) if *n < 0 => format!("({})", n),
+            Expr::Num(n) => n.to_string(),
+            Expr::Neg(expr) => format!("(-{})", expr.parenthesize()),
+            Expr::Binary(op, lhs, rhs) => format!(
+                "({} {} {})",
+                lhs.parenthesize(),
+                op.symbol(),
+                rhs.parenthesize()
+            ),
+        }
+    }
+
+    /// Only the parentheses needed to parse the expression back the same way under `precedence`.
+    pub fn format(&self, precedence: &Precedence) -> String {
+        self.format_absorbing(precedence).0
+    }
+
+    // also returns the lowest left binding power of an operator that, written after
+    // the expression, would become part of it instead of taking it as left operand
+    fn format_absorbing(&self, precedence: &Precedence) -> (String, u8) {
+        let prefix_bp = precedence.prefix_binding_power();
+        match self {
+            Expr::Num(n) if *n < 0 => (n.to_string(), prefix_bp),
+            Expr::Num(n) => (n.to_string(), u8::MAX),
+            Expr::Neg(expr) => {
+                let (inner, absorbs) = expr.format_absorbing(precedence);
+                match **expr {
+                    Expr::Binary(op, ..) if precedence.infix_binding_power(op).0 < prefix_bp => {
+                        (format!("-({})", inner), prefix_bp)
+                    }
+                    _ => (format!("-{}", inner), absorbs.min(prefix_bp)),
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (left_bp, right_bp) = precedence.infix_binding_power(*op);
+                let (lhs, lhs_absorbs) = lhs.format_absorbing(precedence);
+                let lhs = if left_bp >= lhs_absorbs {
+                    format!("({})", lhs)
+                } else {
+                    lhs
+                };
+                let (rhs, rhs_absorbs) = match (&**rhs, rhs.format_absorbing(precedence)) {
+                    (Expr::Binary(rhs_op, ..), (rhs, _))
+                        if precedence.infix_binding_power(*rhs_op).0 < right_bp =>
+                    {
+                        (format!("({})", rhs), u8::MAX)
+                    }
+                    (_, formatted) => formatted,
+                };
+                (
+                    format!("{} {} {}", lhs, op.symbol(), rhs),
+                    right_bp.min(rhs_absorbs),
+                )
+            }
+        }
+    }
+
+    // replaces the first operation on numbers, in evaluation order, by its value
+    fn reduce_step(&mut self) -> Result<bool, String> {
+        match self {
+            Expr::Num(_) => return Ok(false),
+            Expr::Neg(expr) => {
+                if expr.reduce_step()? {
+                    return Ok(true);
+                }
+            }
+            Expr::Binary(_, lhs, rhs) => {
+                if lhs.reduce_step()? || rhs.reduce_step()? {
+                    return Ok(true);
+                }
+            }
+        }
+        *self = Expr::Num(self.eval()?);
+        Ok(true)
+    }
+
+    /// The expression after every single reduction, from the expression itself down to its value.
+    pub fn reductions(&self, precedence: &Precedence) -> Result<Vec<String>, String> {
+        let mut expr = self.clone();
+        let mut trace = vec![expr.format(precedence)];
+        while expr.reduce_step()? {
+            let step = expr.format(precedence);
+            // negating a literal does not change how it looks
+            if trace.last() != Some(&step) {
+                trace.push(step);
+            }
+        }
+        Ok(trace)
+    }
+}
+
+pub fn evaluate_expression(expr: &str, precedence: &Precedence) -> Result<i64, String> {
+    parse(expr, precedence)?.eval()
+}
+
+/// Prints the value of an expression under `table`, or the precedence of both parts,
+/// optionally fully parenthesized and with every reduction step.
+pub fn print_evaluation(expr: &str, table: Option<&str>, parenthesize: bool, steps: bool) {
+    let tables = match table {
+        Some(table) => vec![("VALUE".to_string(), table)],
+        None => vec![
+            ("PART 1".to_string(), PART_1),
+            ("PART 2".to_string(), PART_2),
+        ],
+    };
+    for (name, table) in tables {
+        let lines = Precedence::parse(table).and_then(|precedence| {
+            let ast = parse(expr, &precedence)?;
+            let mut lines = vec![format!("{}: {}", name, ast.eval()?)];
+            if parenthesize {
+                lines.push(format!("    {}", ast.parenthesize()));
+            }
+            if steps {
+                for (i, step) in ast.reductions(&precedence)?.iter().enumerate() {
+                    lines.push(format!("  {} {}", if i == 0 { " " } else { "=" }, step));
                 }
-            },
-            _ => {
-                let digit = c.to_digit(10).unwrap() as usize;
-                curr_state.do_op(digit);
             }
+            Ok(lines)
+        });
+        match lines {
+            Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+            Err(e) => eprintln!("ERR: {}: {}", name, e),
         }
     }
-    curr_state.value() as usize
 }
 
-fn sum_expressions(input: &str, part: usize) -> usize {
+fn sum_expressions(input: &str, precedence: &Precedence) -> i64 {
     input
         .lines()
-        .map(|line| evaluate_expression(line, part))
+        .map(|line| evaluate_expression(line, precedence).expect("Invalid expression.."))
         .sum()
 }
 
@@ -103,16 +435,16 @@ pub fn main() {
     let input = read_to_string("inputs/day18.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "1451467526514";
-    let part_1: usize = sum_expressions(&input, 1);
+    let part_1: i64 = sum_expressions(&input, &Precedence::part(1));
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "224973686321527";
-    let part_2: usize = sum_expressions(&input, 2);
+    let part_2: i64 = sum_expressions(&input, &Precedence::part(2));
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), &known_answer, duration);
 }
@@ -124,80 +456,235 @@ mod tests {
     #[test]
     fn test_example_1() {
         let input: &str = "1 + 2 * 3 + 4 * 5 + 6";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 71);
     }
     #[test]
     fn test_example_2() {
         let input: &str = "1 + (2 * 3) + (4 * (5 + 6))";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 51);
     }
     #[test]
     fn test_example_3() {
         let input: &str = "2 * 3 + (4 * 5)";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 26);
     }
     #[test]
     fn test_example_4() {
         let input: &str = "5 + (8 * 3 + 9 + 3 * 4 * 3)";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 437);
     }
     #[test]
     fn test_example_5() {
         let input: &str = "5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 12240);
     }
     #[test]
     fn test_example_6() {
         let input: &str = "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2";
-        let answer = evaluate_expression(&input, 1);
+        let answer = evaluate_expression(&input, &Precedence::part(1)).unwrap();
         assert_eq!(answer, 13632);
     }
 
     #[test]
     fn test_example_2_0() {
         let input: &str = "4 * 9 + 3";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 48);
     }
     #[test]
     fn test_example_2_1() {
         let input: &str = "1 + 2 * 3 + 4 * 5 + 6";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 231);
     }
     #[test]
     fn test_example_2_2() {
         let input: &str = "1 + (2 * 3) + (4 * (5 + 6))";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 51);
     }
     #[test]
     fn test_example_2_3() {
         let input: &str = "2 * 3 + (4 * 5)";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 46);
     }
     #[test]
     fn test_example_2_4() {
         let input: &str = "5 + (8 * 3 + 9 + 3 * 4 * 3)";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 1445);
     }
     #[test]
     fn test_example_2_5() {
         let input: &str = "5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 669060);
     }
     #[test]
     fn test_example_2_6() {
         let input: &str = "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2";
-        let answer = evaluate_expression(&input, 2);
+        let answer = evaluate_expression(&input, &Precedence::part(2)).unwrap();
         assert_eq!(answer, 23340);
     }
+
+    #[test]
+    fn test_extended_syntax() {
+        assert_eq!(
+            evaluate_expression("12 * 10 - 100 / 7", &Precedence::part(1)),
+            Ok(2)
+        );
+        assert_eq!(
+            evaluate_expression("12 * 10 - 100 / 7", &Precedence::part(2)),
+            Ok(-154)
+        );
+        assert_eq!(
+            evaluate_expression("-2 ^ 2 + 2 ^ 3 ^ 2", &Precedence::part(1)),
+            Ok(508)
+        );
+        assert_eq!(
+            evaluate_expression("3 * -(4 + -1)", &Precedence::part(2)),
+            Ok(-9)
+        );
+        assert_eq!(
+            evaluate_expression("10 - 4 - 3", &Precedence::part(1)),
+            Ok(3)
+        );
+        assert_eq!(
+            parse("1 + 2 * 3", &Precedence::part(2)),
+            Ok(Expr::Binary(
+                BinOp::Mul,
+                Box::new(Expr::Binary(
+                    BinOp::Add,
+                    Box::new(Expr::Num(1)),
+                    Box::new(Expr::Num(2))
+                )),
+                Box::new(Expr::Num(3))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |expr| evaluate_expression(expr, &Precedence::part(1)).unwrap_err();
+        assert_eq!(error("2 % 3"), "unexpected character '%' at 2");
+        assert_eq!(error("(1 + 2"), "expected ')' at 6");
+        assert_eq!(error("1 + 2)"), "unexpected token at 5");
+        assert_eq!(error("1 +"), "unexpected end of expression");
+        assert_eq!(error("1 / (2 - 2)"), "division by zero");
+        assert_eq!(error("2 ^ -1"), "negative exponent in 2^-1");
+        assert_eq!(error("10 ^ 19"), "overflow");
+    }
+
+    #[test]
+    fn test_precedence_tables() {
+        let eval = |expr, table| evaluate_expression(expr, &Precedence::parse(table).unwrap());
+        assert_eq!(eval("1 + 2 * 3 + 4 * 5 + 6", "* > +"), Ok(33));
+        assert_eq!(eval("1 + 2 * 3 + 4 * 5 + 6", "+ > *"), Ok(231));
+        assert_eq!(eval("2 ^ 3 ^ 2", "* > +"), Ok(64));
+        assert_eq!(eval("2 ^ 3 ^ 2", "* > +, right-assoc ^"), Ok(512));
+        assert_eq!(eval("10 - 4 - 3", "+ > *, right-assoc + -"), Ok(9));
+        assert_eq!(eval("2 * 3 ^ 2", "* > ^ > +"), Ok(36));
+        assert_eq!(
+            Precedence::parse(PART_1),
+            Precedence::parse("+ = - = * = /, left-assoc + *, right-assoc ^")
+        );
+
+        let error = |table| Precedence::parse(table).unwrap_err();
+        assert_eq!(error("+ > %"), "unknown operator '%'");
+        assert_eq!(error("+ > * > +"), "operator '+' is ranked twice");
+        assert_eq!(
+            error("+ > *, - > /"),
+            "more than one precedence chain: '- > /'"
+        );
+        assert_eq!(
+            error("+ = ^, right-assoc ^"),
+            "operators on the same level need the same associativity"
+        );
+    }
+
+    #[test]
+    fn test_parenthesize() {
+        let parenthesize =
+            |expr, part| parse(expr, &Precedence::part(part)).unwrap().parenthesize();
+        assert_eq!(parenthesize("1 + 2 * 3 + 4", 1), "(((1 + 2) * 3) + 4)");
+        assert_eq!(parenthesize("1 + 2 * 3 + 4", 2), "((1 + 2) * (3 + 4))");
+        assert_eq!(
+            parenthesize("-2 ^ 3 ^ 2 * -(1)", 1),
+            "((-(2 ^ (3 ^ 2))) * (-1))"
+        );
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let tables = [
+            PART_1,
+            PART_2,
+            "* > +",
+            "- > ^ > *, right-assoc - +",
+            "^ = * = /, right-assoc ^ * /",
+        ];
+        let exprs = [
+            "1 + (2 * 3) + (4 * (5 + 6))",
+            "2 ^ (3 ^ 2) - (2 ^ 3) ^ 2",
+            "-(1 + 2) * -3 ^ -(2 - 1) - --4",
+            "(10 - 4) - 3 - (2 - 1) / (8 / 4 / 2)",
+        ];
+        for table in tables.iter() {
+            let precedence = Precedence::parse(table).unwrap();
+            for expr in exprs.iter() {
+                let ast = parse(expr, &precedence).unwrap();
+                let formatted = ast.format(&precedence);
+                assert_eq!(
+                    parse(&formatted, &precedence),
+                    Ok(ast.clone()),
+                    "{}",
+                    formatted
+                );
+                // the full parentheses make the precedence irrelevant
+                assert_eq!(parse(&ast.parenthesize(), &Precedence::part(1)), Ok(ast));
+            }
+        }
+        let ast = parse("(1 + 2) * (3 * 4)", &Precedence::part(1)).unwrap();
+        assert_eq!(ast.format(&Precedence::part(1)), "1 + 2 * (3 * 4)");
+        assert_eq!(ast.format(&Precedence::part(2)), "1 + 2 * (3 * 4)");
+        let ast = parse("2 * 3 + 4", &Precedence::part(2)).unwrap();
+        assert_eq!(ast.format(&Precedence::part(1)), "2 * (3 + 4)");
+    }
+
+    #[test]
+    fn test_reductions() {
+        let reductions = |expr, part| {
+            let precedence = Precedence::part(part);
+            parse(expr, &precedence).unwrap().reductions(&precedence)
+        };
+        assert_eq!(
+            reductions("1 + (2 * 3) + (4 * (5 + 6))", 1).unwrap(),
+            vec![
+                "1 + (2 * 3) + (4 * (5 + 6))",
+                "1 + 6 + (4 * (5 + 6))",
+                "7 + (4 * (5 + 6))",
+                "7 + (4 * 11)",
+                "7 + 44",
+                "51"
+            ]
+        );
+        assert_eq!(
+            reductions("2 * 3 + (4 * 5)", 2).unwrap(),
+            vec!["2 * 3 + (4 * 5)", "2 * 3 + 20", "2 * 23", "46"]
+        );
+        assert_eq!(
+            reductions("-(2 - 5) * -1", 1).unwrap(),
+            vec!["-(2 - 5) * -1", "--3 * -1", "3 * -1", "-3"]
+        );
+        assert_eq!(
+            reductions("1 + 2 / 0", 2),
+            Err("division by zero".to_string())
+        );
+    }
 }
@@ -1,143 +1,88 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::grammar::Grammar;
+use crate::util::{print_part_1, print_part_2, start_part};
+use itertools::Itertools;
 use std::fs::read_to_string;
-use std::time::Instant;
 
-#[derive(Clone)]
-struct Rule {
-    successors: Vec<Vec<usize>>,
-    literal: bool,
-    value: char,
-}
+// rule 8 and 11 loop in part 2
+pub const PATCH_FILE: &str = "inputs/day19_patch.txt";
 
-impl Rule {
-    fn empty() -> Self {
-        Rule {
-            successors: vec![],
-            literal: false,
-            value: '*',
-        }
-    }
-    fn match_rule(&self, poss_match: &str, rules: &Vec<Rule>) -> Vec<usize> {
-        if self.literal {
-            return if poss_match.chars().nth(0).unwrap() == self.value {
-                vec![1]
-            } else {
-                vec![]
-            };
-        }
-        let mut offsets = Vec::new();
-        for succ_list in self.successors.iter() {
-            let mut local_offsets = vec![0];
-            for rule_index in succ_list.iter() {
-                local_offsets = local_offsets
-                    .iter()
-                    .map(|&offset| {
-                        if offset == poss_match.len() {
-                            vec![]
-                        } else {
-                            let rule = &rules[*rule_index];
-                            let result: Vec<usize> = rule
-                                .match_rule(&poss_match[offset..], rules)
-                                .iter()
-                                .map(|r| r + offset)
-                                .collect();
-                            if result.len() == 0 {
-                                vec![]
-                            } else {
-                                result
-                            }
-                        }
-                    })
-                    .flatten()
-                    .collect();
-            }
-            offsets.extend(local_offsets);
-        }
-        offsets
+// the rules before the first empty line of `input`, patched
+fn load_grammar(input: &str, patch: Option<&str>) -> Result<Grammar, String> {
+    let rules = input.split("\n\n").next().unwrap();
+    let mut grammar = Grammar::parse(rules)?;
+    if let Some(patch) = patch {
+        grammar.patch(patch).map_err(|e| format!("patch: {}", e))?;
     }
+    grammar.rule("0").ok_or("there is no rule 0")?;
+    Ok(grammar)
 }
 
-fn validate_rules(input: &str, part: usize) -> usize {
-    let mut parts = input.split("\r\n\r\n");
-    let rules_s = parts.next().unwrap();
-
-    let num_rules = rules_s.lines().count();
-    let mut rules: Vec<Rule> = vec![Rule::empty(); num_rules];
-    for line in rules_s.lines() {
-        let div = line.split(": ").collect::<Vec<&str>>();
-        let index = div[0].parse::<usize>().expect("Invalid index..");
-        let content = div[1];
-        let rule = match content {
-            "\"a\"" => Rule {
-                successors: vec![],
-                literal: true,
-                value: 'a',
-            },
-            "\"b\"" => Rule {
-                successors: vec![],
-                literal: true,
-                value: 'b',
-            },
-            _ => {
-                let mut successors = Vec::new();
-                for list in content.split("|") {
-                    successors.push(
-                        list.split_whitespace()
-                            .map(|i| i.parse::<usize>().expect("Invalid index in succ list.."))
-                            .collect(),
-                    );
-                }
-                Rule {
-                    successors: successors,
-                    literal: false,
-                    value: '*',
-                }
-            }
-        };
-        if index > rules.len() {
-            rules.resize(index + 1, Rule::empty());
-        }
-        rules[index] = rule;
-    }
-
-    // part 2: replace rules to introduce loops
-    if part == 2 {
-        rules[8].successors = vec![vec![42], vec![42, 8]];
-        rules[11].successors = vec![vec![42, 31], vec![42, 11, 31]];
-    }
-
-    let poss_matches = parts.next().unwrap();
+fn validate_rules(input: &str, patch: Option<&str>) -> usize {
+    let input = input.replace("\r\n", "\n");
+    let grammar = load_grammar(&input, patch).expect("Invalid rules..");
+    // a regex for part 1, part 2 loops and needs the general parser
+    let matcher = grammar.matcher(grammar.rule("0").unwrap());
 
+    let poss_matches = input.split("\n\n").nth(1).unwrap_or_default();
     poss_matches
         .lines()
-        .map(|poss_match| {
-            // check if the whole string was validated
-            if rules[0]
-                .match_rule(poss_match, &rules)
-                .contains(&poss_match.len())
-            {
-                1
+        .filter(|poss_match| matcher.matches(poss_match))
+        .count()
+}
+
+/// Prints the derivation of `message` from rule 0, indented or in DOT format,
+/// or how far it matches when it does not.
+pub fn print_explanation(input: &str, patch: Option<&str>, message: &str, dot: bool) {
+    let input = input.replace("\r\n", "\n");
+    let grammar = match load_grammar(&input, patch) {
+        Ok(grammar) => grammar,
+        Err(e) => {
+            eprintln!("ERR: {}", e);
+            return;
+        }
+    };
+    match grammar.explain(grammar.rule("0").unwrap(), message) {
+        Ok(tree) if dot => print!("{}", tree.to_dot(&grammar)),
+        Ok(tree) => print!("{}", tree.indented(&grammar, message)),
+        Err(failure) => {
+            let expected = if failure.expected.is_empty() {
+                "the end of the message".to_string()
             } else {
-                0
-            }
-        })
-        .sum()
+                failure
+                    .expected
+                    .iter()
+                    .map(|(rule, literal)| format!("{:?} (rule {})", literal, grammar.name(*rule)))
+                    .join(" or ")
+            };
+            let found = match message[failure.matched..].chars().next() {
+                Some(c) => format!("{:?}", c),
+                None => "the end of the message".to_string(),
+            };
+            println!(
+                "NO MATCH after {:?}: expected {}, found {}",
+                &message[..failure.matched],
+                expected,
+                found
+            );
+        }
+    }
 }
 
 pub fn main() {
     let input = read_to_string("inputs/day19.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "299";
-    let part_1: usize = validate_rules(&input, 1);
+    let part_1: usize = validate_rules(&input, None);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "414";
-    let part_2: usize = validate_rules(&input, 2);
+    let patch = read_to_string(PATCH_FILE).expect("Rule patch not found..");
+    let part_2: usize = validate_rules(&input, Some(&patch));
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), &known_answer, duration);
 }
@@ -150,21 +95,149 @@ mod tests {
     fn test_example_1() {
         let input: &str =
             "0: 4 1 5\n1: 2 3 | 3 2\n2: 4 4 | 5 5\n3: 4 5 | 5 4\n4: \"a\"\n5: \"b\"\r\n\r\nababbb\nbababa\nabbbab\naaabbb\naaaabbb";
-        let answer: usize = validate_rules(&input, 1);
+        let answer: usize = validate_rules(&input, None);
         assert_eq!(answer, 2);
     }
 
     #[test]
     fn test_example_2() {
         let input = read_to_string("inputs/day19_test.txt").expect("Input not found..");
-        let answer: usize = validate_rules(&input, 1);
+        let answer: usize = validate_rules(&input, None);
         assert_eq!(answer, 3);
     }
 
     #[test]
     fn test_example_3() {
         let input = read_to_string("inputs/day19_test.txt").expect("Input not found..");
-        let answer: usize = validate_rules(&input, 2);
+        let patch = read_to_string(PATCH_FILE).expect("Rule patch not found..");
+        let answer: usize = validate_rules(&input, Some(&patch));
         assert_eq!(answer, 12);
     }
+
+    // the recursive matcher from before the Earley parser, kept as a reference for the benchmark:
+    // every end offset at which `rule` can match a prefix of `message`
+    fn recursive_offsets(
+        rules: &[(Vec<Vec<usize>>, Option<char>)],
+        rule: usize,
+        message: &str,
+    ) -> Vec<usize> {
+        let (successors, literal) = &rules[rule];
+        if let Some(c) = literal {
+            return if message.starts_with(*c) {
+                vec![1]
+            } else {
+                vec![]
+            };
+        }
+        let mut offsets = Vec::new();
+        for sequence in successors.iter() {
+            let mut local_offsets = vec![0];
+            for &next in sequence.iter() {
+                local_offsets = local_offsets
+                    .iter()
+                    .filter(|&&offset| offset < message.len())
+                    .flat_map(|&offset| {
+                        recursive_offsets(rules, next, &message[offset..])
+                            .into_iter()
+                            .map(move |end| end + offset)
+                    })
+                    .collect();
+            }
+            offsets.extend(local_offsets);
+        }
+        offsets
+    }
+
+    fn recursive_rules(rules: &str) -> Vec<(Vec<Vec<usize>>, Option<char>)> {
+        let mut parsed = vec![(Vec::new(), None); rules.lines().count()];
+        for line in rules.lines() {
+            let mut split = line.split(": ");
+            let index: usize = split.next().unwrap().parse().unwrap();
+            let content = split.next().unwrap();
+            if index >= parsed.len() {
+                parsed.resize(index + 1, (Vec::new(), None));
+            }
+            parsed[index] = match content.strip_prefix('"') {
+                Some(literal) => (Vec::new(), literal.chars().next()),
+                None => (
+                    content
+                        .split('|')
+                        .map(|list| {
+                            list.split_whitespace()
+                                .map(|i| i.parse().unwrap())
+                                .collect()
+                        })
+                        .collect(),
+                    None,
+                ),
+            };
+        }
+        parsed
+    }
+
+    #[test]
+    fn test_recursive_reference() {
+        let input = read_to_string("inputs/day19_test.txt")
+            .unwrap()
+            .replace("\r\n", "\n");
+        let mut parts = input.split("\n\n");
+        let rules = recursive_rules(parts.next().unwrap());
+        let count = parts
+            .next()
+            .unwrap()
+            .lines()
+            .filter(|m| recursive_offsets(&rules, 0, m).contains(&m.len()))
+            .count();
+        assert_eq!(count, validate_rules(&input, None));
+    }
+
+    // cargo test --release benchmark_matchers -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_matchers() {
+        use crate::grammar::Matcher;
+        use std::time::Instant;
+
+        let input = read_to_string("inputs/day19.txt")
+            .unwrap()
+            .replace("\r\n", "\n");
+        let grammar = load_grammar(&input, None).unwrap();
+        let start = grammar.rule("0").unwrap();
+        let messages: Vec<&str> = input.split("\n\n").nth(1).unwrap().lines().collect();
+
+        let timer = Instant::now();
+        let regex = grammar.to_regex(start).unwrap();
+        println!("compiling the regex: {:?}", timer.elapsed());
+        for (name, matcher) in [
+            ("regex", Matcher::Regex(regex)),
+            (
+                "earley",
+                Matcher::Earley {
+                    grammar: &grammar,
+                    start,
+                },
+            ),
+        ]
+        .iter()
+        {
+            let timer = Instant::now();
+            let count = messages.iter().filter(|m| matcher.matches(m)).count();
+            println!("{:<10}{} matches in {:?}", name, count, timer.elapsed());
+            assert_eq!(count, 299);
+        }
+
+        let rules = recursive_rules(input.split("\n\n").next().unwrap());
+        let timer = Instant::now();
+        let count = messages
+            .iter()
+            .filter(|m| recursive_offsets(&rules, 0, m).contains(&m.len()))
+            .count();
+        println!(
+            "{:<10}{} matches in {:?}",
+            "recursive",
+            count,
+            timer.elapsed()
+        );
+        assert_eq!(count, 299);
+    }
 }
@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn verify_password_part1(input: &str) -> bool {
     let low: usize = input.split('-').collect::<Vec<&str>>()[0]
@@ -53,7 +52,7 @@ fn verify_password_part2(input: &str) -> bool {
 pub fn main() {
     let input = read_to_string("inputs/day2.txt").expect("Input not found..");
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "666";
     let part_1: usize = input
         .lines()
@@ -63,7 +62,7 @@ pub fn main() {
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "670";
     let part_2: usize = input
         .lines()
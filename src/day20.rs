@@ -1,394 +1,673 @@
-use crate::util::{print_part_1, print_part_2};
-use std::collections::HashMap;
+use crate::raster::Raster;
+use crate::util::{print_part_1, print_part_2, start_part};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
 enum TileType {
     DOT,
     HASH,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+type Grid = Vec<Vec<TileType>>;
+type Cells = Vec<Vec<(usize, TileType)>>; // every cell with the id of its tile
+
+// rotates a grid 90° to the left, works for any rectangle
+fn rotate<T: Copy>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
+    let width = grid.first().map_or(0, |row| row.len());
+    (0..width)
+        .map(|i| grid.iter().map(|row| row[width - (i + 1)]).collect())
+        .collect()
+}
+
+// mirrors a grid left to right
+fn flip<T: Copy>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
+    grid.iter()
+        .map(|row| row.iter().rev().copied().collect())
+        .collect()
+}
+
+/// One of the 8 orientations of a grid: mirrored for 4..8, then rotated `orientation % 4` times.
+fn orient<T: Copy>(grid: &[Vec<T>], orientation: usize) -> Vec<Vec<T>> {
+    let mut grid = if orientation >= 4 {
+        flip(grid)
+    } else {
+        grid.to_vec()
+    };
+    for _ in 0..orientation % 4 {
+        grid = rotate(&grid);
+    }
+    grid
+}
+
+#[derive(PartialEq, Debug, Clone)]
 struct Tile {
-    grid: [[TileType; 10]; 10],
+    grid: Grid, // square, of any size
     id: usize,
-    neighbours: [usize; 4],
 }
 
+type Edge = u128; // bit i is set when cell i of the edge is a '#'
+const MAX_SIZE: usize = 128;
+
+fn to_edge<'a>(cells: impl Iterator<Item = &'a TileType>) -> Edge {
+    cells
+        .enumerate()
+        .filter(|(_, &cell)| cell == TileType::HASH)
+        .fold(0, |edge, (i, _)| edge | 1 << i)
+}
+
+// the same for an edge and the edge read the other way round, so for tiles that fit in any orientation
+fn canonical(edge: Edge, size: usize) -> Edge {
+    edge.min(edge.reverse_bits() >> (MAX_SIZE - size))
+}
+
+// edges in the order of `Tile::edges`
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
 impl Tile {
-    fn empty() -> Self {
+    fn from_lines(lines: &str) -> Result<Self, String> {
+        let mut lines = lines.lines();
+        let header = lines.next().unwrap_or_default();
+        let id = header
+            .strip_prefix("Tile ")
+            .and_then(|header| header.strip_suffix(':'))
+            .and_then(|id| id.parse::<usize>().ok())
+            .ok_or_else(|| format!("invalid tile header '{}'", header))?;
+        let grid = lines
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '.' => Ok(TileType::DOT),
+                        '#' => Ok(TileType::HASH),
+                        _ => Err(format!("invalid character '{}' in tile {}", c, id)),
+                    })
+                    .collect()
+            })
+            .collect::<Result<Grid, String>>()?;
+        if grid.is_empty() || grid.iter().any(|row| row.len() != grid.len()) {
+            return Err(format!("tile {} is not square", id));
+        }
+        Ok(Self { grid, id })
+    }
+
+    fn size(&self) -> usize {
+        self.grid.len()
+    }
+
+    // top, right, bottom and left, read left to right and top to bottom
+    fn edges(&self) -> [Edge; 4] {
+        let last = self.size() - 1;
+        [
+            to_edge(self.grid[0].iter()),
+            to_edge(self.grid.iter().map(|row| &row[last])),
+            to_edge(self.grid[last].iter()),
+            to_edge(self.grid.iter().map(|row| &row[0])),
+        ]
+    }
+
+    fn oriented(&self, orientation: usize) -> Self {
         Self {
-            grid: [[TileType::DOT; 10]; 10],
-            id: 0,
-            neighbours: [0; 4],
+            grid: orient(&self.grid, orientation),
+            id: self.id,
         }
     }
-    fn from_lines(lines: &str) -> Self {
-        let mut grid = [[TileType::DOT; 10]; 10];
-        let mut lines = lines.lines();
-        let id = lines.next().unwrap().split(" ").collect::<Vec<&str>>()[1]
-            .split(":")
-            .collect::<Vec<&str>>()[0]
-            .parse::<usize>()
-            .unwrap();
-        for (i, line) in lines.enumerate() {
-            for (j, c) in line.chars().enumerate() {
-                grid[i][j] = match c {
-                    '.' => TileType::DOT,
-                    '#' => TileType::HASH,
-                    _ => unreachable!(),
-                };
-            }
+}
+
+fn read_tiles(input: &str) -> Result<Vec<Tile>, String> {
+    let tiles = input
+        .replace("\r\n", "\n")
+        .split("\n\n")
+        .filter(|tile_lines| !tile_lines.trim().is_empty())
+        .map(Tile::from_lines)
+        .collect::<Result<Vec<Tile>, String>>()?;
+    let size = tiles.first().ok_or("there are no tiles")?.size();
+    if size > MAX_SIZE {
+        return Err(format!("tiles are at most {}x{}", MAX_SIZE, MAX_SIZE));
+    }
+    // without the borders nothing of a smaller tile would be left for the image
+    if size < 3 {
+        return Err("tiles are at least 3x3".to_string());
+    }
+    let mut ids = HashSet::new();
+    for tile in tiles.iter() {
+        if tile.size() != size {
+            return Err(format!(
+                "tile {} is {}x{}, the others are {}x{}",
+                tile.id,
+                tile.size(),
+                tile.size(),
+                size,
+                size
+            ));
         }
-        Self {
-            grid: grid,
-            id: id,
-            neighbours: [0; 4],
+        if !ids.insert(tile.id) {
+            return Err(format!("tile {} appears twice", tile.id));
         }
     }
+    Ok(tiles)
+}
 
-    // (bool, bool, usize, usize) == (has match, was flipped, index self, index other)
-    fn can_neighbour(&self, other: &Tile) -> (bool, bool, usize, usize) {
-        for (j, o_edge) in other.get_edges(false).iter().enumerate() {
-            for (i, edge) in self.get_edges(false).iter().enumerate() {
-                if edge == o_edge {
-                    return (true, false, i, j);
-                }
-            }
+/// The tiles, oriented and in place, row by row.
+struct Assembly {
+    width: usize,
+    height: usize,
+    tiles: Vec<Tile>,
+}
 
-            for (i, edge) in self.get_edges(true).iter().enumerate() {
-                if edge == o_edge {
-                    return (true, true, i, j);
-                }
-            }
-        }
-        (false, false, 0, 0)
+impl Assembly {
+    fn ids(&self) -> Vec<Vec<usize>> {
+        self.tiles
+            .chunks(self.width)
+            .map(|row| row.iter().map(|tile| tile.id).collect())
+            .collect()
     }
 
-    fn get_edges(&self, flipped: bool) -> Vec<Vec<TileType>> {
-        let mut edges = vec![vec![], vec![], vec![], vec![]];
+    fn corners(&self) -> Vec<usize> {
+        let ids = self.ids();
+        let (last_row, last_col) = (self.height - 1, self.width - 1);
+        let mut corners = vec![
+            ids[0][0],
+            ids[0][last_col],
+            ids[last_row][0],
+            ids[last_row][last_col],
+        ];
+        corners.dedup(); // on a single row or column
+        corners.sort_unstable();
+        corners.dedup();
+        corners
+    }
 
-        for i in 0..self.grid.len() {
-            edges[0].push(self.grid[0][i]); // top
-            edges[1].push(self.grid[self.grid.len() - 1][i]); // bot
-            edges[2].push(self.grid[i][0]); // left
-            edges[3].push(self.grid[i][self.grid.len() - 1]); // right
-        }
-        if flipped {
-            edges[0].reverse();
-            edges[1].reverse();
-            edges[2].reverse();
-            edges[3].reverse();
+    fn cells(&self) -> Cells {
+        let mut cells = Vec::new();
+        for row in self.tiles.chunks(self.width) {
+            for i in 0..row[0].size() {
+                cells.push(
+                    row.iter()
+                        .flat_map(|tile| tile.grid[i].iter().map(move |&cell| (tile.id, cell)))
+                        .collect(),
+                );
+            }
         }
-        edges
+        cells
+    }
+
+    /// The cell of the whole assembly, borders included, that a cell of the image comes from.
+    fn image_to_assembly(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        let size = self.tiles[0].size();
+        let inner = size - 2;
+        (
+            (row / inner) * size + row % inner + 1,
+            (col / inner) * size + col % inner + 1,
+        )
     }
 
-    fn flip_horizontal_axis(&mut self) {
-        let mut grid = self.grid.clone();
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid.len() {
-                grid[i][j] = self.grid[self.grid.len() - (i + 1)][j];
+    /// The tiles with their ids in the lines between them, `marked` cells as `O`.
+    fn to_ascii(&self, marked: &HashSet<(usize, usize)>) -> String {
+        let size = self.tiles[0].size();
+        let mut ascii = String::new();
+        for (tile_row, row) in self.tiles.chunks(self.width).enumerate() {
+            for tile in row {
+                // as wide as the tile, even when the id is not
+                let label = format!("-{}", tile.id);
+                ascii.push('+');
+                ascii.extend(label.chars().chain(std::iter::repeat('-')).take(size));
+            }
+            ascii += "+\n";
+            for i in 0..size {
+                for (tile_col, tile) in row.iter().enumerate() {
+                    ascii.push('|');
+                    for (j, cell) in tile.grid[i].iter().enumerate() {
+                        ascii.push(
+                            match (
+                                marked.contains(&(tile_row * size + i, tile_col * size + j)),
+                                cell,
+                            ) {
+                                (true, _) => 'O',
+                                (false, TileType::HASH) => '#',
+                                (false, TileType::DOT) => '.',
+                            },
+                        );
+                    }
+                }
+                ascii += "|\n";
             }
         }
-        self.grid = grid;
+        ascii += &format!("+{}", "-".repeat(size)).repeat(self.width);
+        ascii += "+\n";
+        ascii
     }
 
-    fn flip_vertical_axis(&mut self) {
-        let mut grid = self.grid.clone();
-        for i in 0..grid.len() {
-            grid[i].reverse();
+    /// One pixel per cell with a line around every tile, borders darker, `marked` cells red.
+    fn to_raster(&self, marked: &HashSet<(usize, usize)>) -> Raster {
+        let size = self.tiles[0].size();
+        let mut raster = Raster::new(
+            self.width * (size + 1) + 1,
+            self.height * (size + 1) + 1,
+            [0, 0, 0],
+        );
+        for (row, cells) in self.cells().iter().enumerate() {
+            for (col, (_, cell)) in cells.iter().enumerate() {
+                let border =
+                    [0, size - 1].contains(&(row % size)) || [0, size - 1].contains(&(col % size));
+                let color = match (marked.contains(&(row, col)), border, cell) {
+                    (true, _, _) => [230, 60, 30],
+                    (false, false, TileType::HASH) => [130, 180, 230],
+                    (false, false, TileType::DOT) => [10, 50, 110],
+                    (false, true, TileType::HASH) => [70, 95, 120],
+                    (false, true, TileType::DOT) => [5, 25, 55],
+                };
+                raster.set(col + col / size + 1, row + row / size + 1, color);
+            }
         }
-        self.grid = grid;
+        raster
     }
 
-    // rotates grid 90° to the left
-    fn rotate(&mut self) {
-        let mut grid = self.grid.clone();
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid.len() {
-                grid[i][j] = self.grid[j][self.grid.len() - (i + 1)];
+    /// The picture: all tiles next to each other, without their borders.
+    fn image(&self) -> Grid {
+        let inner = self.tiles[0].size().saturating_sub(2);
+        let mut image = Vec::new();
+        for row in self.tiles.chunks(self.width) {
+            for i in 1..=inner {
+                image.push(
+                    row.iter()
+                        .flat_map(|tile| tile.grid[i][1..=inner].iter().copied())
+                        .collect(),
+                );
             }
         }
-        self.grid = grid;
+        image
     }
 }
 
-fn rotate_grid(grid: &mut Vec<Vec<&TileType>>) {
-    let old_grid = grid.clone();  // use old values when calculating new ones
-    for i in 0..grid.len() {
-        for j in 0..grid.len() {
-            grid[i][j] = old_grid[j][grid.len() - (i + 1)];
-        }
-    }
+/// The tiles by their edges, whichever way round.
+struct EdgeIndex {
+    size: usize,
+    tiles: HashMap<Edge, Vec<usize>>, // canonical edge -> the index of every tile with that edge
 }
 
-fn find_tile(tiles: &Vec<Tile>, tile_id: usize) -> &Tile {
-    for tile in tiles {
-        if tile.id == tile_id {
-            return tile;
+impl EdgeIndex {
+    fn new(tiles: &[Tile]) -> Self {
+        let size = tiles.first().map_or(0, Tile::size);
+        let mut index: HashMap<Edge, Vec<usize>> = HashMap::new();
+        for (i, tile) in tiles.iter().enumerate() {
+            for &edge in tile.edges().iter() {
+                index.entry(canonical(edge, size)).or_default().push(i);
+            }
         }
+        EdgeIndex { size, tiles: index }
     }
-    println!("Asked to find tile: {}", tile_id);
-    unreachable!();
-}
 
-fn edge_unique(tiles: &Vec<Tile>, edge: &Vec<TileType>, owner: usize) -> bool {
-    for tile in tiles {
-        if tile.id == owner {
-            continue;
+    // the other tiles that have this edge, in some orientation
+    fn neighbours(&self, tile: usize, edge: Edge) -> impl Iterator<Item = usize> + '_ {
+        self.tiles[&canonical(edge, self.size)]
+            .iter()
+            .copied()
+            .filter(move |&other| other != tile)
+            .dedup()
+    }
+
+    // the sides (TOP, RIGHT, ..) of a tile whose edge fits no other tile
+    fn unmatched_sides(&self, tile: usize, tiles: &[Tile]) -> Vec<usize> {
+        let edges = tiles[tile].edges();
+        (0..4)
+            .filter(|&side| self.neighbours(tile, edges[side]).next().is_none())
+            .collect()
+    }
+
+    /// The ids of the four corners, when they follow from the edges alone: no edge fits more
+    /// than one other tile, the corners have unmatched edges on two adjacent sides, and the
+    /// corners, sides (one unmatched edge) and inner tiles (none) add up to a board of w by h.
+    fn corners(&self, tiles: &[Tile]) -> Option<Vec<usize>> {
+        if self.tiles.values().any(|with_edge| with_edge.len() > 2) {
+            return None;
         }
-        for tile_edge in tile.get_edges(false) {
-            if tile_edge == *edge {
-                return false;
+        let (mut corners, mut sides, mut inner) = (Vec::new(), 0, 0);
+        for i in 0..tiles.len() {
+            match self.unmatched_sides(i, tiles).as_slice() {
+                [] => inner += 1,
+                [_] => sides += 1,
+                // TOP and BOTTOM or LEFT and RIGHT would make it a piece of a single row
+                [a, b] if (a + b) % 2 == 1 => corners.push(tiles[i].id),
+                _ => return None,
             }
         }
-        for tile_edge in tile.get_edges(true) {
-            if tile_edge == *edge {
-                return false;
-            }
+        let fits_board = (2..=tiles.len() / 2).any(|w| {
+            let h = tiles.len() / w;
+            tiles.len().is_multiple_of(w)
+                && h >= 2
+                && sides == 2 * (w - 2) + 2 * (h - 2)
+                && inner == (w - 2) * (h - 2)
+        });
+        if corners.len() == 4 && fits_board {
+            Some(corners)
+        } else {
+            None
         }
     }
-    true
 }
 
-fn rearrange_tiles(input: &str, part: usize) -> usize {
-    // have to find the corner pieces,
-    // a.k.a. the ones with only two possible neighbours
-
-    let mut tiles = input
-        .split("\r\n\r\n")
-        .map(|tile_lines| Tile::from_lines(tile_lines))
-        .collect::<Vec<Tile>>();
-
-    let mut corners = Vec::new();
-    let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+/// Finds all ways to put the tiles together, by placing them row by row.
+struct Solver {
+    oriented: Vec<Tile>, // all 8 orientations of every tile, those of tile i at 8 * i..8 * (i + 1)
+    right_of: Vec<Vec<usize>>, // the oriented tiles that fit to the right of an oriented tile
+    below: Vec<Vec<usize>>,
+}
 
-    for tile1 in tiles.iter() {
-        for tile2 in tiles.iter() {
-            if tile1.id == tile2.id {
-                continue;
+impl Solver {
+    fn new(tiles: &[Tile]) -> Self {
+        let index = EdgeIndex::new(tiles);
+        let oriented: Vec<Tile> = tiles
+            .iter()
+            .flat_map(|tile| (0..8).map(move |o| tile.oriented(o)))
+            .collect();
+        let edges: Vec<[Edge; 4]> = oriented.iter().map(Tile::edges).collect();
+        let mut right_of = vec![Vec::new(); oriented.len()];
+        let mut below = vec![Vec::new(); oriented.len()];
+        for a in 0..oriented.len() {
+            // only the tiles that share the edge, in the orientations where it lines up
+            for tile in index.neighbours(a / 8, edges[a][RIGHT]) {
+                right_of[a].extend(
+                    (8 * tile..8 * (tile + 1)).filter(|&b| edges[b][LEFT] == edges[a][RIGHT]),
+                );
             }
-            let neighbour_match = tile1.can_neighbour(tile2);
-            if neighbour_match.0 {
-                neighbours.entry(tile1.id).or_default().push(tile2.id);
+            for tile in index.neighbours(a / 8, edges[a][BOTTOM]) {
+                below[a].extend(
+                    (8 * tile..8 * (tile + 1)).filter(|&b| edges[b][TOP] == edges[a][BOTTOM]),
+                );
             }
         }
-    }
-
-    for tile in tiles.iter_mut() {
-        let mut i = 0;
-        for &id in neighbours[&tile.id].iter() {
-            tile.neighbours[i] = id;
-            i += 1;
-        }
-        if i == 2 {
-            corners.push(tile.id);
+        Solver {
+            oriented,
+            right_of,
+            below,
         }
     }
-    if part == 1 {
-        let mut corners_id_mult = 1;
-        for tile_id in corners.iter() {
-            corners_id_mult *= tile_id;
-        }
-        return corners_id_mult;
-    }
-
-    /* IDEA:
-     * - pick some corner piece
-     * - it has to have 2 unique edges
-     * - orient such that they are top and left edge
-     */
-    let tile_grid_size = (tiles.len() as f64).sqrt() as usize;
-    let mut oriented_tiles = vec![vec![Tile::empty(); tile_grid_size]; tile_grid_size];
-    let mut all_fixed_tiles = Vec::new();
-    let mut top_left = find_tile(&tiles, corners[0]).to_owned();
-
-    loop {
-        let mut i = 0;
-        let mut edge_orientation = [0; 2];
-        for (side, corner_edge) in top_left.get_edges(false).iter().enumerate() {
-            if edge_unique(&tiles, &corner_edge, top_left.id) {
-                edge_orientation[i] = side;
-                i += 1;
+
+    // stops once a second, different assembly is found
+    fn search(
+        &self,
+        width: usize,
+        height: usize,
+        placed: &mut Vec<usize>,
+        used: &mut Vec<bool>,
+        found: &mut Vec<(Assembly, Cells)>,
+    ) {
+        let pos = placed.len();
+        if pos == width * height {
+            let assembly = Assembly {
+                width,
+                height,
+                tiles: placed.iter().map(|&o| self.oriented[o].clone()).collect(),
+            };
+            // the same assembly is found once for every orientation of the board
+            let cells = assembly.cells();
+            let canonical = (0..8).map(|o| orient(&cells, o)).min().unwrap();
+            if found.iter().all(|(_, other)| *other != canonical) {
+                found.push((assembly, canonical));
             }
+            return;
         }
-        if [[0, 2], [2, 0]].contains(&edge_orientation) {
-            break;
+        let (col, row) = (pos % width, pos / width);
+        let candidates: Vec<usize> = match (col, row) {
+            (0, 0) => (0..self.oriented.len()).collect(),
+            (0, _) => self.below[placed[pos - width]].clone(),
+            (_, 0) => self.right_of[placed[pos - 1]].clone(),
+            _ => self.right_of[placed[pos - 1]]
+                .iter()
+                .filter(|o| self.below[placed[pos - width]].contains(o))
+                .copied()
+                .collect(),
+        };
+        for o in candidates {
+            // a tile with an edge that fits nothing has to be on that side of the board
+            if used[o / 8]
+                || (col + 1 < width && self.right_of[o].is_empty())
+                || (row + 1 < height && self.below[o].is_empty())
+            {
+                continue;
+            }
+            used[o / 8] = true;
+            placed.push(o);
+            self.search(width, height, placed, used, found);
+            placed.pop();
+            used[o / 8] = false;
+            if found.len() > 1 {
+                return;
+            }
         }
-        top_left.rotate();
     }
-    oriented_tiles[0][0] = top_left;
-    all_fixed_tiles.push(top_left);
+}
 
-    let mut tiles_todo: Vec<(usize, usize, (usize, usize))> = Vec::new();
-    for &n in &top_left.neighbours {
-        if n != 0 {
-            tiles_todo.push((n, top_left.id, (0, 0)));
+/// The only way to put all tiles together in a rectangle, of any width and height.
+fn assemble(tiles: &[Tile]) -> Result<Assembly, String> {
+    let solver = Solver::new(tiles);
+    let mut found = Vec::new();
+    // a board of h by w is a rotated board of w by h
+    for width in
+        (1..=tiles.len()).filter(|w| tiles.len().is_multiple_of(*w) && w * w <= tiles.len())
+    {
+        let height = tiles.len() / width;
+        let mut used = vec![false; tiles.len()];
+        solver.search(width, height, &mut Vec::new(), &mut used, &mut found);
+        if found.len() > 1 {
+            break;
         }
     }
+    let describe = |assembly: &Assembly| {
+        format!(
+            "{}x{} with tile {} in a corner",
+            assembly.width,
+            assembly.height,
+            assembly.corners()[0]
+        )
+    };
+    match found.len() {
+        0 => Err("the tiles cannot be put together in a rectangle".to_string()),
+        1 => Ok(found.pop().unwrap().0),
+        _ => Err(format!(
+            "ambiguous, the tiles fit together in more than one way: {} and {}",
+            describe(&found[0].0),
+            describe(&found[1].0)
+        )),
+    }
+}
 
-    loop {
-        // exit condition
-        if tiles_todo.len() == 0 {
-            break;
-        }
+// '#' is part of the monster, anything else may be anything
+const SEA_MONSTER: &str = "                  # \n#    ##    ##    ###\n #  #  #  #  #  #   ";
 
-        // main loop
-        let (tile_id, parent_id, (x, y)) = tiles_todo.pop().unwrap();
-        let mut tile = find_tile(&tiles, tile_id).to_owned();
-        let parent: &Tile = find_tile(&all_fixed_tiles, parent_id);
-
-        loop {
-            let match_description = tile.can_neighbour(parent);
-            assert_eq!(match_description.0, true);
-
-            if match_description.1 {
-                // some flip needs to occur
-                if [2, 3].contains(&match_description.2) {
-                    // matched on a side, so flip horizontal
-                    tile.flip_horizontal_axis();
-                } else {
-                    tile.flip_vertical_axis();
-                }
-            } else {
-                // no flips occured
-                if [[2, 3], [3, 2], [1, 0], [0, 1]]
-                    .contains(&[match_description.2, match_description.3])
-                {
-                    // valid match
-                    break;
-                } else {
-                    // still invalid, give it a turn
-                    tile.rotate();
-                }
-            }
-        }
+/// The shape of a monster, cropped to the `#`s.
+struct Pattern {
+    grid: Vec<Vec<bool>>,
+}
 
-        // Structure tiles back together in one big grid
-        let match_description = tile.can_neighbour(parent);
-        let new_coords;
-        match match_description.3 {
-            // where is this tile located compared to the parent
-            0 => {
-                // top
-                new_coords = (x - 1, y);
-            }
-            1 => {
-                // bottom
-                new_coords = (x + 1, y);
-            }
-            2 => {
-                // left
-                new_coords = (x, y - 1);
-            }
-            3 => {
-                // right
-                new_coords = (x, y + 1);
-            }
-            _ => unreachable!(),
-        }
-        oriented_tiles[new_coords.0][new_coords.1] = tile;
-        all_fixed_tiles.push(tile);
-
-        // Add new tiles to the queue
-        'outer: for &to_check in &tile.neighbours {
-            for seen_tile in all_fixed_tiles.iter() {
-                if to_check == 0 || to_check == seen_tile.id {
-                    continue 'outer;
-                }
-            }
-            tiles_todo.push((to_check, tile.id, new_coords));
-        }
+impl Pattern {
+    fn parse(text: &str) -> Result<Self, String> {
+        let grid: Vec<Vec<bool>> = text
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect())
+            .collect();
+        let rows: Vec<usize> = (0..grid.len())
+            .filter(|&i| grid[i].contains(&true))
+            .collect();
+        let cols: Vec<usize> = (0..grid.iter().map(Vec::len).max().unwrap_or(0))
+            .filter(|&j| grid.iter().any(|row| row.get(j) == Some(&true)))
+            .collect();
+        let (top, left) = match (rows.first(), cols.first()) {
+            (Some(&top), Some(&left)) => (top, left),
+            _ => return Err("the monster pattern has no '#'".to_string()),
+        };
+        let (bottom, right) = (*rows.last().unwrap(), *cols.last().unwrap());
+        let grid = grid[top..=bottom]
+            .iter()
+            .map(|row| (left..=right).map(|j| row.get(j) == Some(&true)).collect())
+            .collect();
+        Ok(Pattern { grid })
     }
 
-    // Collect all tiles
-    let mut full_grid: Vec<Vec<TileType>> =
-        vec![vec![TileType::DOT; oriented_tiles.len() * 10]; oriented_tiles.len() * 10];
-    let mut full_grid_ids: Vec<Vec<usize>> =
-        vec![vec![0; oriented_tiles.len()]; oriented_tiles.len()];
-    for (i, tile_row) in oriented_tiles.iter().enumerate() {
-        for (j, tile) in tile_row.iter().enumerate() {
-            for (k, grid_row) in tile.grid.iter().enumerate() {
-                for (l, point) in grid_row.iter().enumerate() {
-                    full_grid[k + 10 * i][l + 10 * j] = *point;
-                    full_grid_ids[i][j] = tile.id;
-                }
-            }
-        }
+    fn size(&self) -> usize {
+        self.grid.iter().flatten().filter(|&&cell| cell).count()
     }
+}
+
+/// A monster in the image, its cells in the orientation it was found in.
+#[derive(Debug, PartialEq)]
+struct Monster {
+    row: usize, // of the top left corner of the (oriented) pattern
+    col: usize,
+    orientation: usize,
+    cells: Vec<(usize, usize)>,
+}
 
-    // Remove border tiles
-    let mut borderless_grid = Vec::new();
-    for (i, row) in full_grid.iter().enumerate() {
-        if i % 10 == 0 || (i + 1) % 10 == 0 {
+/// Every place the pattern fits the image, in any of its 8 orientations. Monsters may overlap.
+fn find_monsters(image: &[Vec<TileType>], pattern: &Pattern) -> Vec<Monster> {
+    let (height, width) = (image.len(), image.first().map_or(0, |row| row.len()));
+    let mut shapes: Vec<Vec<Vec<bool>>> = Vec::new();
+    let mut monsters = Vec::new();
+    for orientation in 0..8 {
+        let shape = orient(&pattern.grid, orientation);
+        // symmetric patterns look the same in several orientations
+        if shapes.contains(&shape) {
             continue;
         }
-        let mut new_row = vec![];
-        for (j, tile) in row.iter().enumerate() {
-            if j % 10 == 0 || (j + 1) % 10 == 0 {
-                continue;
-            }
-            new_row.push(tile);
-        }
-        borderless_grid.push(new_row);
-    }
-
-    let monster = [
-        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0],
-        [1, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1, 1],
-        [0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 0],
-    ];
-
-    let monster_size = 15; // monster occupies 15 HASHes
-    let mut num_monsters = 0;
-    let grid_size = borderless_grid.len();
-
-    for _ in 0..4 {
-        // 4 rotations, might have to add flipping too if solution is not reached
-        for (i, row) in borderless_grid[..grid_size - 3].iter().enumerate() {
-            'monster_pos: for (j, _) in row[..grid_size - 20].iter().enumerate() {
-                for m_i in 0..3 {
-                    for m_j in 0..20 {
-                        if monster[m_i][m_j] == 1
-                            && *borderless_grid[i + m_i][j + m_j] != TileType::HASH
-                        {
-                            continue 'monster_pos;
-                        }
-                    }
+        let offsets: Vec<(usize, usize)> = (0..shape.len())
+            .flat_map(|i| (0..shape[i].len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| shape[i][j])
+            .collect();
+        for row in 0..(height + 1).saturating_sub(shape.len()) {
+            for col in 0..(width + 1).saturating_sub(shape[0].len()) {
+                if offsets
+                    .iter()
+                    .all(|(i, j)| image[row + i][col + j] == TileType::HASH)
+                {
+                    monsters.push(Monster {
+                        row,
+                        col,
+                        orientation,
+                        cells: offsets.iter().map(|(i, j)| (row + i, col + j)).collect(),
+                    });
                 }
-                num_monsters += 1;
             }
         }
+        shapes.push(shape);
+    }
+    monsters
+}
 
-        if num_monsters > 0 {
-            break;
+/// The number of `#`s that are not part of any monster.
+fn roughness(image: &[Vec<TileType>], monsters: &[Monster]) -> usize {
+    let monster_cells: HashSet<&(usize, usize)> = monsters
+        .iter()
+        .flat_map(|monster| monster.cells.iter())
+        .collect();
+    let hashes = image
+        .iter()
+        .flatten()
+        .filter(|&&x| x == TileType::HASH)
+        .count();
+    hashes - monster_cells.len()
+}
+
+fn rearrange_tiles(input: &str, part: usize) -> Result<usize, String> {
+    let tiles = read_tiles(input)?;
+    if part == 1 {
+        // the puzzle input has unique edges, so the corners are known without assembling anything
+        let corners = match EdgeIndex::new(&tiles).corners(&tiles) {
+            Some(corners) => corners,
+            None => assemble(&tiles)?.corners(),
+        };
+        return Ok(corners.iter().product());
+    }
+    let assembly = assemble(&tiles)?;
+    let image = assembly.image();
+    let pattern = Pattern::parse(SEA_MONSTER).unwrap();
+    Ok(roughness(&image, &find_monsters(&image, &pattern)))
+}
+
+/// Prints where the monsters of `pattern` (the sea monster by default) are in the image.
+pub fn print_monsters(input: &str, pattern: Option<&str>) {
+    let found = read_tiles(input)
+        .and_then(|tiles| assemble(&tiles))
+        .and_then(|assembly| {
+            let pattern = Pattern::parse(pattern.unwrap_or(SEA_MONSTER))?;
+            Ok((assembly.image(), pattern))
+        });
+    let (image, pattern) = match found {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("ERR: {}", e);
+            return;
         }
-        rotate_grid(&mut borderless_grid);
+    };
+    let monsters = find_monsters(&image, &pattern);
+    for monster in monsters.iter() {
+        println!(
+            "monster at row {}, column {} in orientation {}",
+            monster.row, monster.col, monster.orientation
+        );
     }
+    let cells = monsters.len() * pattern.size();
+    let overlapping = cells
+        - monsters
+            .iter()
+            .flat_map(|m| m.cells.iter())
+            .unique()
+            .count();
+    println!(
+        "{} monsters in a {}x{} image, {} cells shared by several, roughness {}",
+        monsters.len(),
+        image.first().map_or(0, |row| row.len()),
+        image.len(),
+        overlapping,
+        roughness(&image, &monsters)
+    );
+}
 
-    borderless_grid
+// the assembly and the cells of all monsters in it
+fn find_in_assembly(
+    input: &str,
+    pattern: Option<&str>,
+) -> Result<(Assembly, HashSet<(usize, usize)>), String> {
+    let pattern = Pattern::parse(pattern.unwrap_or(SEA_MONSTER))?;
+    let assembly = assemble(&read_tiles(input)?)?;
+    let monsters = find_monsters(&assembly.image(), &pattern);
+    let cells = monsters
         .iter()
-        .flatten()
-        .filter(|&&&x| x == TileType::HASH)
-        .count()
-        - monster_size * num_monsters
+        .flat_map(|monster| monster.cells.iter())
+        .map(|&cell| assembly.image_to_assembly(cell))
+        .collect();
+    Ok((assembly, cells))
+}
+
+/// The assembled tiles as text, tile ids in the lines between them and monsters drawn with `O`.
+pub fn render_ascii(input: &str, pattern: Option<&str>) -> Result<String, String> {
+    let (assembly, monsters) = find_in_assembly(input, pattern)?;
+    Ok(assembly.to_ascii(&monsters))
+}
+
+/// The assembled tiles as an image, one pixel per cell and monsters in red.
+pub fn render_raster(input: &str, pattern: Option<&str>) -> Result<Raster, String> {
+    let (assembly, monsters) = find_in_assembly(input, pattern)?;
+    Ok(assembly.to_raster(&monsters))
 }
 
 pub fn main() {
     let input = read_to_string("inputs/day20.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "27803643063307";
-    let part_1: usize = rearrange_tiles(&input, 1);
+    let part_1: usize = rearrange_tiles(&input, 1).expect("Invalid tiles..");
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "1644";
-    let part_2: usize = rearrange_tiles(&input, 2);
+    let part_2: usize = rearrange_tiles(&input, 2).expect("Invalid tiles..");
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), &known_answer, duration);
 }
@@ -400,14 +679,250 @@ mod tests {
     #[test]
     fn test_example_1() {
         let input = read_to_string("inputs/day20_test.txt").expect("Input not found..");
-        let answer: usize = rearrange_tiles(&input, 1);
+        let answer: usize = rearrange_tiles(&input, 1).unwrap();
         assert_eq!(answer, 20899048083289);
     }
 
     #[test]
     fn test_example_2() {
         let input = read_to_string("inputs/day20_test.txt").expect("Input not found..");
-        let answer: usize = rearrange_tiles(&input, 2);
+        let answer: usize = rearrange_tiles(&input, 2).unwrap();
         assert_eq!(answer, 273);
     }
+
+    #[test]
+    fn test_edge_index() {
+        // #..#. read the other way round
+        assert_eq!(canonical(0b01001, 5), 0b01001);
+        assert_eq!(canonical(0b10010, 5), 0b01001);
+        assert_eq!(canonical(0b11, 2), 0b11);
+
+        let input = read_to_string("inputs/day20_test.txt").expect("Input not found..");
+        let tiles = read_tiles(&input).unwrap();
+        let index = EdgeIndex::new(&tiles);
+        let neighbours = |id| {
+            let i = tiles.iter().position(|tile| tile.id == id).unwrap();
+            let mut ids: Vec<usize> = tiles[i]
+                .edges()
+                .iter()
+                .flat_map(|&edge| index.neighbours(i, edge))
+                .map(|j| tiles[j].id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(neighbours(1951), vec![2311, 2729]);
+        assert_eq!(neighbours(1427), vec![1489, 2311, 2473, 2729]);
+        let mut corners = index.corners(&tiles).unwrap();
+        corners.sort_unstable();
+        assert_eq!(corners, vec![1171, 1951, 2971, 3079]);
+
+        // the corners of a single row are not known from its edges
+        let (_, row) = puzzle(1, 4, 10);
+        let tiles = read_tiles(&row).unwrap();
+        assert_eq!(EdgeIndex::new(&tiles).corners(&tiles), None);
+        assert_eq!(rearrange_tiles(&row, 1).unwrap(), {
+            let ids = assemble(&tiles).unwrap().corners();
+            ids[0] * ids[1]
+        });
+    }
+
+    #[test]
+    fn test_render() {
+        let input = read_to_string("inputs/day20_test.txt").expect("Input not found..");
+        let ascii = render_ascii(&input, None).unwrap();
+        let lines: Vec<&str> = ascii.lines().collect();
+        // 3 by 3 tiles of 10 by 10 and the lines around them
+        assert_eq!(lines.len(), 34);
+        assert!(lines.iter().all(|line| line.len() == 34));
+        assert!(lines[0].starts_with("+-") && lines[33] == "+----------".repeat(3) + "+");
+        for id in &[1951, 2311, 3079, 2729, 1427, 2473, 2971, 1489, 1171] {
+            assert!(ascii.contains(&format!("+-{}-----", id)));
+        }
+        // 2 monsters of 15 cells
+        assert_eq!(ascii.matches('O').count(), 30);
+
+        let raster = render_raster(&input, None).unwrap();
+        assert_eq!(&raster.to_ppm()[..13], b"P6\n34 34\n255\n");
+
+        // ids longer than the tiles are cut off
+        let ascii = render_ascii("Tile 12345:\n#..\n...\n..#", None).unwrap();
+        assert_eq!(ascii.lines().next(), Some("+-12+"));
+        assert!(ascii.lines().all(|line| line.len() == 5));
+    }
+
+    // a pseudo random picture of `rows` by `cols` tiles of `size` by `size`, neighbouring
+    // tiles share their border, and that picture cut into tiles
+    fn puzzle(rows: usize, cols: usize, size: usize) -> (Grid, String) {
+        let step = size - 1;
+        let mut seed: u64 = 0x2020_1220;
+        let picture: Grid = (0..rows * step + 1)
+            .map(|_| {
+                (0..cols * step + 1)
+                    .map(|_| {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 7;
+                        seed ^= seed << 17;
+                        if (seed >> 40) & 1 == 0 {
+                            TileType::DOT
+                        } else {
+                            TileType::HASH
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut tiles = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                let mut tile = format!("Tile {}:", 10 * r + c + 1);
+                for row in &picture[r * step..=r * step + step] {
+                    tile.push('\n');
+                    tile.extend(
+                        row[c * step..=c * step + step]
+                            .iter()
+                            .map(|cell| match cell {
+                                TileType::DOT => '.',
+                                TileType::HASH => '#',
+                            }),
+                    );
+                }
+                tiles.push(tile);
+            }
+        }
+        // without the borders of the tiles
+        let image = picture
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % step != 0)
+            .map(|(_, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(j, _)| j % step != 0)
+                    .map(|(_, cell)| *cell)
+                    .collect()
+            })
+            .collect();
+        (image, tiles.join("\n\n"))
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        for &(rows, cols, size) in [(2, 3, 10), (1, 4, 10), (3, 3, 12), (4, 2, 9)].iter() {
+            let (image, input) = puzzle(rows, cols, size);
+            let assembly = assemble(&read_tiles(&input).unwrap()).unwrap();
+            assert_eq!(assembly.width.min(assembly.height), rows.min(cols));
+            assert_eq!(assembly.width.max(assembly.height), rows.max(cols));
+            let corners = vec![1, cols, 10 * (rows - 1) + 1, 10 * (rows - 1) + cols];
+            assert_eq!(
+                assembly.corners(),
+                corners.into_iter().unique().collect_vec()
+            );
+            assert!((0..8).any(|o| orient(&image, o) == assembly.image()));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |input: &str| assemble(&read_tiles(input)?).map(|_| ());
+        assert_eq!(error(""), Err("there are no tiles".to_string()));
+        assert_eq!(
+            error("Tile 1:\n##.\n#..\n..#"),
+            Ok(()) // a single tile always fits
+        );
+        // nothing would be left of them without their borders
+        assert_eq!(
+            error("Tile 1:\n##\n#."),
+            Err("tiles are at least 3x3".to_string())
+        );
+        assert_eq!(
+            error("Tile 1:\n#"),
+            Err("tiles are at least 3x3".to_string())
+        );
+        assert_eq!(
+            error("Tile 1:\n##.\n#..\n...\n\nTile 2:\n#...\n....\n....\n...."),
+            Err("tile 2 is 4x4, the others are 3x3".to_string())
+        );
+        assert_eq!(
+            error("Tile 1:\n#..\n.x.\n..."),
+            Err("invalid character 'x' in tile 1".to_string())
+        );
+        assert_eq!(
+            error("Tile 1:\n##.\n#..\n...\n\nTile 1:\n##.\n#..\n..."),
+            Err("tile 1 appears twice".to_string())
+        );
+        assert_eq!(
+            error("Tile 1:\n###\n###\n###\n\nTile 2:\n...\n...\n..."),
+            Err("the tiles cannot be put together in a rectangle".to_string())
+        );
+        // every edge of a blank tile fits every other blank one
+        let blank = "\n...\n...\n...";
+        let input = format!("Tile 1:{}\n\nTile 2:{}\n\nTile 3:{}", blank, blank, blank);
+        assert!(error(&input).unwrap_err().starts_with("ambiguous"));
+
+        // a row whose last tile also fits in front of its first one: any tile can start it
+        let (_, row) = puzzle(1, 4, 10);
+        let mut tiles: Vec<Vec<String>> = row
+            .split("\n\n")
+            .map(|tile| tile.lines().map(str::to_string).collect())
+            .collect();
+        let left: Vec<char> = tiles[0]
+            .iter()
+            .skip(1)
+            .map(|line| line.as_bytes()[0] as char)
+            .collect();
+        for (line, first) in tiles[3].iter_mut().skip(1).zip(left) {
+            line.pop();
+            line.push(first);
+        }
+        let ring = tiles.iter().map(|tile| tile.join("\n")).join("\n\n");
+        assert!(rearrange_tiles(&ring, 1)
+            .unwrap_err()
+            .starts_with("ambiguous"));
+    }
+
+    fn image(lines: &[&str]) -> Grid {
+        lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|c| {
+                        if c == '#' {
+                            TileType::HASH
+                        } else {
+                            TileType::DOT
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_monsters() {
+        let pattern = Pattern::parse("\n  #..  \n  ###\n\n").unwrap();
+        assert_eq!(pattern.grid, vec![vec![true, false, false], vec![true; 3]]);
+        assert_eq!(pattern.size(), 4);
+        assert!(Pattern::parse(" . \n").is_err());
+
+        // two upright monsters, one standing on its tail, one upside down
+        // sharing three cells with the second and a # that is no monster
+        let image = image(&[
+            "#......#", //
+            "###....#", //
+            "......##", //
+            "..#.....", //
+            "..###...", //
+            "....#..#",
+        ]);
+        let monsters = find_monsters(&image, &pattern);
+        let found: Vec<(usize, usize, usize)> = monsters
+            .iter()
+            .map(|m| (m.row, m.col, m.orientation))
+            .collect();
+        assert_eq!(found, vec![(0, 0, 0), (3, 2, 0), (0, 6, 1), (4, 2, 2)]);
+        assert_eq!(monsters[0].cells, vec![(0, 0), (1, 0), (1, 1), (1, 2)]);
+        // 14 #s, 13 of them in monsters
+        assert_eq!(roughness(&image, &monsters), 1);
+    }
 }
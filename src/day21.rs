@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 struct Ingredient {
     name: String,
@@ -102,14 +101,14 @@ pub fn main() {
     let input = read_to_string("inputs/day21.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2315";
     let part_1: String = match_allergens(&input, 1);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "cfzdnz,htxsjf,ttbrlvd,bbbl,lmds,cbmjz,cmbcm,dvnbh";
     let part_2: String = match_allergens(&input, 2);
     let duration = start.elapsed();
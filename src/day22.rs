@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::{HashSet, VecDeque};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn winner_score(deck: &VecDeque<usize>) -> usize {
     let mut score = 0;
@@ -87,7 +86,7 @@ pub fn main() {
     let (p1, p2) = parse_input(&input);
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "30780";
     let part_1: usize = combat(
         &mut p1.iter().map(|&c| c).collect(),
@@ -99,7 +98,7 @@ pub fn main() {
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "36621";
     let part_2: usize = combat(
         &mut p1.iter().map(|&c| c).collect(),
@@ -1,5 +1,4 @@
-use crate::util::{print_part_1, print_part_2};
-use std::time::Instant;
+use crate::util::{print_part_1, print_part_2, start_part};
 
 fn next_dest(curr: usize, min: usize, max: usize) -> usize {
     // custom overflow rules
@@ -94,14 +93,14 @@ pub fn main() {
     let input = "315679824";
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "72496583";
     let part_1: String = part1(&input, 100);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "41785843847";
     let part_2: usize = part2(&input, 10_000_000);
     let duration = start.elapsed();
@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::HashMap;
 use std::fs::read_to_string;
-use std::time::Instant;
 
 //                                             NORTH  EAST
 fn reduce_directions(directions: Vec<&str>) -> (i64, i64) {
@@ -137,14 +136,14 @@ pub fn main() {
     let input = read_to_string("inputs/day24.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "438";
     let part_1: usize = part1(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "4038";
     let part_2: usize = part2(&input, 100);
     let duration = start.elapsed();
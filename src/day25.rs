@@ -1,5 +1,4 @@
-use crate::util::print_part_1;
-use std::time::Instant;
+use crate::util::{print_part_1, start_part};
 
 fn encryption_key(card: usize, door: usize) -> usize {
     let mut card_loop_size = 0;
@@ -21,7 +20,7 @@ pub fn main() {
     let public_key_door = 8719412;
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "9620012";
     let part_1: usize = encryption_key(public_key_card, public_key_door);
     let duration = start.elapsed();
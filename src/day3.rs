@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn count_trees_slope(map: &str, right: usize, down: usize) -> usize {
     let mut x: usize = 0; // current offset in line
@@ -24,14 +23,14 @@ fn count_trees_slope(map: &str, right: usize, down: usize) -> usize {
 pub fn main() {
     let map = read_to_string("inputs/day3.txt").expect("Input not found..");
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "252";
     let part_1: usize = count_trees_slope(&map, 3, 1);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "2608962048";
     let part_2: usize = count_trees_slope(&map, 1, 1)
         * count_trees_slope(&map, 3, 1)
@@ -1,98 +1,98 @@
-use crate::util::{print_part_1, print_part_2};
-use regex::Regex;
+use crate::schema::{Problem, Schema};
+use crate::util::{print_part_1, print_part_2, start_part};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fs::read_to_string;
-use std::time::Instant;
 
-fn verify_password(pass: &str, verify_value: bool) -> bool {
-    let required_fields = ["byr:", "iyr:", "eyr:", "hgt:", "hcl:", "ecl:", "pid:"]; // doesn't include country id field
-    for field in required_fields.iter() {
-        if !pass.contains(field) {
-            return false;
+// cid is optional, to get by with a North Pole Credential
+pub const PUZZLE_SCHEMA: &str = "\
+byr: range 1920-2002
+iyr: range 2010-2020
+eyr: range 2020-2030
+hgt: range 150-193 cm, 59-76 in
+hcl: regex #[0-9a-f]{6}
+ecl: enum amb blu brn gry grn hzl oth
+pid: regex [0-9]{9}
+cid: optional
+";
+
+// passports are separated by empty lines
+fn read_passports(input: &str) -> Vec<String> {
+    input
+        .replace("\r\n", "\n")
+        .split("\n\n")
+        .filter(|passport| !passport.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn count_valid_passports(input: &str, schema: &Schema, verify_value: bool) -> usize {
+    read_passports(input)
+        .iter()
+        .filter(|passport| schema.is_valid(passport, verify_value))
+        .count()
+}
+
+/// Prints how many passports of the batch follow `schema`, the puzzle's rules by default,
+/// how often each field was wrong in which way, and with `list` what is wrong with every passport.
+pub fn print_passports(input: &str, schema: Option<&str>, list: bool) {
+    let schema = match Schema::parse(schema.unwrap_or(PUZZLE_SCHEMA)) {
+        Ok(schema) => schema,
+        Err(e) => {
+            eprintln!("ERR: schema: {}", e);
+            return;
         }
-        if !verify_value {
-            continue;
+    };
+    let passports = read_passports(input);
+    let mut reasons: HashMap<(String, &str), usize> = HashMap::new();
+    let (mut complete, mut valid) = (0, 0);
+    for (i, passport) in passports.iter().enumerate() {
+        let errors = schema.validate(passport);
+        if errors.is_empty() {
+            valid += 1;
+        }
+        if errors.iter().all(|error| error.problem != Problem::Missing) {
+            complete += 1;
+        }
+        if list && !errors.is_empty() {
+            println!("passport {}: {}", i + 1, errors.iter().join("; "));
+        }
+        for error in errors.iter() {
+            *reasons
+                .entry((error.field.clone(), error.kind()))
+                .or_default() += 1;
         }
-        let value: &str = pass.split(field).collect::<Vec<&str>>()[1]
-            .split_whitespace()
-            .collect::<Vec<&str>>()[0];
-
-        match field {
-            &"byr:" => {
-                let year = value.parse::<usize>().unwrap_or(0);
-                if year < 1920 || year > 2002 {
-                    return false;
-                }
-            }
-            &"iyr:" => {
-                let year = value.parse::<usize>().unwrap_or(0);
-                if year < 2010 || year > 2020 {
-                    return false;
-                }
-            }
-            &"eyr:" => {
-                let year = value.parse::<usize>().unwrap_or(0);
-                if year < 2020 || year > 2030 {
-                    return false;
-                }
-            }
-            &"hgt:" => {
-                if !value.contains("cm") && !value.contains("in") {
-                    return false;
-                }
-                let height = value[..value.len() - 2].parse::<usize>().unwrap();
-                if &value[value.len() - 2..] == "in" {
-                    if height < 59 || height > 76 {
-                        return false;
-                    }
-                } else {
-                    if height < 150 || height > 193 {
-                        return false;
-                    }
-                }
-            }
-            &"hcl:" => {
-                let re = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-                if !re.is_match(value) {
-                    return false;
-                }
-            }
-            &"ecl:" => {
-                if !["amb", "blu", "brn", "gry", "grn", "hzl", "oth"].contains(&value) {
-                    return false;
-                }
-            }
-            &"pid:" => {
-                let re = Regex::new(r"^[0-9]{9}$").unwrap();
-                if !re.is_match(value) {
-                    return false;
-                }
-            }
-            _ => {}
-        };
     }
-    true
+    println!(
+        "{} passports, {} with all required fields, {} valid",
+        passports.len(),
+        complete,
+        valid
+    );
+    let reasons = reasons
+        .into_iter()
+        .sorted_by_key(|((field, kind), count)| (Reverse(*count), field.clone(), *kind));
+    for ((field, kind), count) in reasons {
+        println!("{:>6}  {} {}", count, field, kind);
+    }
 }
 
 pub fn main() {
     let input = read_to_string("inputs/day4.txt").expect("Input not found..");
+    let schema = Schema::parse(PUZZLE_SCHEMA).unwrap();
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "264";
-    let part_1: usize = input
-        .split("\r\n\r\n") // empty lines (this probably depends on the operating system..)
-        .map(|line| if verify_password(line, false) { 1 } else { 0 })
-        .sum();
+    let part_1: usize = count_valid_passports(&input, &schema, false);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "224";
-    let part_2: usize = input
-        .split("\r\n\r\n") // empty lines (this probably depends on the operating system..)
-        .map(|line| if verify_password(line, true) { 1 } else { 0 })
-        .sum();
+    let part_2: usize = count_valid_passports(&input, &schema, true);
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), &known_answer, duration);
 }
@@ -101,6 +101,51 @@ pub fn main() {
 mod tests {
     use super::*;
 
+    fn verify_password(pass: &str, verify_value: bool) -> bool {
+        Schema::parse(PUZZLE_SCHEMA)
+            .unwrap()
+            .is_valid(pass, verify_value)
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let schema = Schema::parse(PUZZLE_SCHEMA).unwrap();
+        let errors = |passport: &str| {
+            schema
+                .validate(passport)
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            errors("hgt:cm ecl:gry ecl:blu pid:86003332 eyr:2040 hcl:#fffffd byr:1937"),
+            vec![
+                "iyr is missing",
+                "eyr is out of range: 2040 is not in 2020-2030",
+                "hgt is malformed: 'cm' is not a number followed by cm or in",
+                "ecl appears 2 times",
+                "pid is malformed: '86003332' is not matching [0-9]{9}"
+            ]
+        );
+        assert_eq!(
+            errors("hgt:190in ecl:xyz byr:x iyr:2010 eyr:2020 hcl:#fffffd pid:000000001"),
+            vec![
+                "byr is malformed: 'x' is not a number",
+                "hgt is out of range: 190in is not in 59-76in",
+                "ecl is malformed: 'xyz' is not one of amb, blu, brn, gry, grn, hzl, oth"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let input = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\r\nbyr:1937 iyr:2017 cid:147 hgt:183cm\r\n\r\niyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n\nhgt:cm ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017\n";
+        let schema = Schema::parse(PUZZLE_SCHEMA).unwrap();
+        assert_eq!(read_passports(input).len(), 3);
+        assert_eq!(count_valid_passports(input, &schema, false), 2);
+        assert_eq!(count_valid_passports(input, &schema, true), 1);
+    }
+
     #[test]
     fn test_example_1() {
         let input: &str =
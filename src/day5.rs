@@ -1,6 +1,5 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 // Seat is specified by a binary number
 // R and B are 1, L and F are 0.
@@ -41,14 +40,14 @@ pub fn main() {
     let mut seat_ids: Vec<usize> = input.lines().map(|line| seat_id(line)).collect();
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "963";
     let part_1: usize = seat_ids.iter().max().unwrap().to_owned();
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "592";
     seat_ids.sort();
     let part_2: usize = get_missing_seat_id(seat_ids);
@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::HashSet;
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn get_unique_chars(answers: &str) -> HashSet<char> {
     let mut chars: HashSet<char> = HashSet::new();
@@ -36,7 +35,7 @@ pub fn main() {
     let input = read_to_string("inputs/day6.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "6930";
     let part_1: usize = input
         .split("\r\n\r\n") // empty lines (this probably depends on the operating system..)
@@ -46,7 +45,7 @@ pub fn main() {
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "3585";
     let part_2: usize = input
         .split("\r\n\r\n") // empty lines (this probably depends on the operating system..)
@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn get_collection_small_to_large(lines: &str) -> HashMap<String, Vec<String>> {
     // Collection holds a 'fits in' relationship
@@ -115,14 +114,14 @@ pub fn main() {
     let input = read_to_string("inputs/day7.txt").expect("Input not found..");
 
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "226";
     let part_1: usize = get_shiny_gold_options(&input);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "9569";
     let part_2: usize = get_total_bags_in_shiny_gold(&input);
     let duration = start.elapsed();
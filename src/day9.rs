@@ -1,7 +1,6 @@
-use crate::util::{print_part_1, print_part_2};
+use crate::util::{print_part_1, print_part_2, start_part};
 use std::collections::VecDeque;
 use std::fs::read_to_string;
-use std::time::Instant;
 
 fn exists_sum(options: &VecDeque<&usize>, target: &usize) -> bool {
     for x in options {
@@ -60,14 +59,14 @@ pub fn main() {
         .map(|line| line.parse::<usize>().expect("Could not parse input.."))
         .collect();
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "14144619";
     let part_1: usize = find_broken_sequence(&input, 25);
     let duration = start.elapsed();
     print_part_1(&part_1.to_string(), &known_answer, duration);
 
     // PART 2
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "1766397";
     let (min, max): (usize, usize) = contiguous_bounds_to_sum(&input, part_1);
     let part_2 = min + max;
@@ -0,0 +1,409 @@
+// Debugger for day 8 programs, driven by one command per line so it can be scripted from stdin:
+//   step [n]              execute n instructions (default 1)
+//   continue              run until a breakpoint, watchpoint or halt
+//   break <pc|opcode>     stop before the instruction at pc, or before any `opcode`
+//   delete <pc|opcode>    remove a breakpoint
+//   watch acc             stop whenever acc changes (`unwatch acc` to stop watching)
+//   patch <pc> <ins>      replace an instruction, e.g. `patch 7 nop -4`
+//   trace [n]             show the last n executed instructions (default all)
+//   list [pc] [n]         show n instructions around pc
+//   print                 show pc, acc and the next instruction
+//   reset                 start over with the (patched) program
+//   quit
+
+use crate::vm::{Halt, Instruction, InstructionSet, Machine, Op};
+use std::io::{self, BufRead, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Breakpoint {
+    Pc(usize),
+    Opcode(Op),
+}
+
+struct TraceEntry {
+    step: usize,
+    pc: usize,
+    ins: Instruction,
+    acc_before: i32,
+    acc_after: i32,
+}
+
+pub struct Debugger<'a> {
+    machine: Machine<'a>,
+    breakpoints: Vec<Breakpoint>,
+    watch_acc: bool,
+    trace: Vec<TraceEntry>,
+    halted: Option<Halt>,
+}
+
+// why execution stopped before the program halted
+enum Stop {
+    Breakpoint(Breakpoint),
+    Watchpoint(i32, i32),
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: Vec<Instruction>, set: &'a InstructionSet) -> Self {
+        Debugger {
+            machine: Machine::new(program, set),
+            breakpoints: Vec::new(),
+            watch_acc: false,
+            trace: Vec::new(),
+            halted: None,
+        }
+    }
+
+    fn format_trace(&self, entry: &TraceEntry) -> String {
+        let mut line = format!(
+            "#{:<5} pc {:>4}: {}",
+            entry.step,
+            entry.pc,
+            self.machine.set.format(&entry.ins)
+        );
+        if entry.acc_before != entry.acc_after {
+            line.push_str(&format!(
+                "\tacc {} -> {}",
+                entry.acc_before, entry.acc_after
+            ));
+        }
+        line
+    }
+
+    fn current(&self) -> String {
+        match self.machine.program.get(self.machine.pc) {
+            Some(ins) => format!(
+                "pc {}: {}\tacc {}",
+                self.machine.pc,
+                self.machine.set.format(ins),
+                self.machine.regs.acc
+            ),
+            None => format!(
+                "pc {}: <end>\tacc {}",
+                self.machine.pc, self.machine.regs.acc
+            ),
+        }
+    }
+
+    // executes one instruction, records it in the trace and returns acc before and after
+    fn step_once(&mut self) -> Result<(i32, i32), Halt> {
+        if let Some(halt) = self.halted {
+            return Err(halt);
+        }
+        let pc = self.machine.pc;
+        let acc_before = self.machine.regs.acc;
+        if let Err(halt) = self.machine.step() {
+            self.halted = Some(halt);
+            return Err(halt);
+        }
+        self.trace.push(TraceEntry {
+            step: self.machine.steps,
+            pc,
+            ins: self.machine.program[pc],
+            acc_before,
+            acc_after: self.machine.regs.acc,
+        });
+        Ok((acc_before, self.machine.regs.acc))
+    }
+
+    fn breakpoint_hit(&self) -> Option<Breakpoint> {
+        let ins = self.machine.program.get(self.machine.pc)?;
+        self.breakpoints.iter().copied().find(|bp| match bp {
+            Breakpoint::Pc(pc) => *pc == self.machine.pc,
+            Breakpoint::Opcode(op) => *op == ins.op,
+        })
+    }
+
+    fn run_until_stop(&mut self) -> Result<Stop, Halt> {
+        let mut first = true;
+        loop {
+            // don't get stuck on the breakpoint we are standing on
+            if !first {
+                if let Some(bp) = self.breakpoint_hit() {
+                    return Ok(Stop::Breakpoint(bp));
+                }
+            }
+            first = false;
+            let (acc_before, acc_after) = self.step_once()?;
+            if self.watch_acc && acc_before != acc_after {
+                return Ok(Stop::Watchpoint(acc_before, acc_after));
+            }
+        }
+    }
+
+    fn parse_breakpoint(&self, arg: &str) -> Result<Breakpoint, String> {
+        if let Ok(pc) = arg.parse::<usize>() {
+            return Ok(Breakpoint::Pc(pc));
+        }
+        self.machine
+            .set
+            .lookup(arg)
+            .map(Breakpoint::Opcode)
+            .ok_or_else(|| format!("unknown pc or opcode '{}'", arg))
+    }
+
+    fn format_breakpoint(&self, bp: Breakpoint) -> String {
+        match bp {
+            Breakpoint::Pc(pc) => format!("pc {}", pc),
+            Breakpoint::Opcode(op) => self.machine.set.name(op).unwrap_or("???").to_string(),
+        }
+    }
+
+    /// Executes one command, returns the output lines (or an error message) and whether to quit.
+    pub fn command(&mut self, line: &str) -> (Vec<String>, bool) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let mut out = Vec::new();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["quit"] | ["q"] => return (out, true),
+            ["step"] | ["s"] => self.cmd_step(1, &mut out),
+            ["step", n] | ["s", n] => match n.parse() {
+                Ok(n) => self.cmd_step(n, &mut out),
+                Err(_) => Err(format!("invalid count '{}'", n)),
+            },
+            ["continue"] | ["c"] => {
+                match self.run_until_stop() {
+                    Ok(Stop::Breakpoint(bp)) => {
+                        out.push(format!("breakpoint at {}", self.format_breakpoint(bp)))
+                    }
+                    Ok(Stop::Watchpoint(old, new)) => {
+                        out.push(format!("watchpoint: acc {} -> {}", old, new))
+                    }
+                    Err(halt) => out.push(format!("halted: {}", halt)),
+                }
+                out.push(self.current());
+                Ok(())
+            }
+            ["break", arg] | ["b", arg] => self.parse_breakpoint(arg).map(|bp| {
+                if !self.breakpoints.contains(&bp) {
+                    self.breakpoints.push(bp);
+                }
+                out.push(format!("breakpoint set at {}", self.format_breakpoint(bp)));
+            }),
+            ["delete", arg] | ["d", arg] => self.parse_breakpoint(arg).map(|bp| {
+                self.breakpoints.retain(|&other| other != bp);
+                out.push(format!(
+                    "breakpoint removed at {}",
+                    self.format_breakpoint(bp)
+                ));
+            }),
+            ["watch", "acc"] | ["w", "acc"] => {
+                self.watch_acc = true;
+                out.push("watching acc".to_string());
+                Ok(())
+            }
+            ["unwatch", "acc"] => {
+                self.watch_acc = false;
+                out.push("no longer watching acc".to_string());
+                Ok(())
+            }
+            ["patch", pc, name, arg] => self.cmd_patch(pc, name, arg, &mut out),
+            ["trace"] | ["t"] => {
+                out.extend(self.trace.iter().map(|e| self.format_trace(e)));
+                Ok(())
+            }
+            ["trace", n] | ["t", n] => match n.parse::<usize>() {
+                Ok(n) => {
+                    let skip = self.trace.len().saturating_sub(n);
+                    out.extend(self.trace[skip..].iter().map(|e| self.format_trace(e)));
+                    Ok(())
+                }
+                Err(_) => Err(format!("invalid count '{}'", n)),
+            },
+            ["list"] | ["l"] => self.cmd_list(self.machine.pc, 5, &mut out),
+            ["list", pc] | ["l", pc] => match pc.parse() {
+                Ok(pc) => self.cmd_list(pc, 5, &mut out),
+                Err(_) => Err(format!("invalid pc '{}'", pc)),
+            },
+            ["list", pc, n] | ["l", pc, n] => match (pc.parse(), n.parse()) {
+                (Ok(pc), Ok(n)) => self.cmd_list(pc, n, &mut out),
+                _ => Err("usage: list [pc] [n]".to_string()),
+            },
+            ["print"] | ["p"] => {
+                out.push(self.current());
+                Ok(())
+            }
+            ["reset"] => {
+                let program = self.machine.program.clone();
+                self.machine = Machine::new(program, self.machine.set);
+                self.trace.clear();
+                self.halted = None;
+                out.push(self.current());
+                Ok(())
+            }
+            _ => Err(format!("unknown command '{}'", line.trim())),
+        };
+        if let Err(e) = result {
+            out.push(format!("error: {}", e));
+        }
+        (out, false)
+    }
+
+    fn cmd_step(&mut self, n: usize, out: &mut Vec<String>) -> Result<(), String> {
+        for _ in 0..n {
+            match self.step_once() {
+                Ok(_) => {
+                    let line = self.format_trace(self.trace.last().unwrap());
+                    out.push(line);
+                }
+                Err(halt) => {
+                    out.push(format!("halted: {}", halt));
+                    break;
+                }
+            }
+        }
+        out.push(self.current());
+        Ok(())
+    }
+
+    fn cmd_patch(
+        &mut self,
+        pc: &str,
+        name: &str,
+        arg: &str,
+        out: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let pc: usize = pc.parse().map_err(|_| format!("invalid pc '{}'", pc))?;
+        let op = self
+            .machine
+            .set
+            .lookup(name)
+            .ok_or_else(|| format!("unknown opcode '{}'", name))?;
+        let arg: i32 = arg
+            .parse()
+            .map_err(|_| format!("invalid argument '{}'", arg))?;
+        let set = self.machine.set;
+        let ins = self
+            .machine
+            .program
+            .get_mut(pc)
+            .ok_or_else(|| format!("pc {} is outside the program", pc))?;
+        let old = set.format(ins);
+        *ins = Instruction { op, arg };
+        out.push(format!("pc {}: {} -> {}", pc, old, set.format(ins)));
+        // a patch can make a halted program runnable again
+        if self.halted.is_some() && self.machine.halt_reason().is_none() {
+            self.halted = None;
+        }
+        Ok(())
+    }
+
+    fn cmd_list(&mut self, pc: usize, n: usize, out: &mut Vec<String>) -> Result<(), String> {
+        let from = pc.saturating_sub(n / 2);
+        let to = (from + n).min(self.machine.program.len());
+        for i in from..to {
+            let marker = if i == self.machine.pc { "=>" } else { "  " };
+            let bp = if self.breakpoints.contains(&Breakpoint::Pc(i)) {
+                "*"
+            } else {
+                " "
+            };
+            out.push(format!(
+                "{}{}{:>4}: {}",
+                marker,
+                bp,
+                i,
+                self.machine.set.format(&self.machine.program[i])
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reads commands from `input` until it ends or `quit`, writing all output to `output`.
+pub fn run<R: BufRead, W: Write>(
+    debugger: &mut Debugger,
+    input: R,
+    mut output: W,
+    prompt: bool,
+) -> io::Result<()> {
+    writeln!(output, "{}", debugger.current())?;
+    if prompt {
+        write!(output, "(dbg) ")?;
+        output.flush()?;
+    }
+    for line in input.lines() {
+        let (lines, quit) = debugger.command(&line?);
+        for line in lines {
+            writeln!(output, "{}", line)?;
+        }
+        if quit {
+            break;
+        }
+        if prompt {
+            write!(output, "(dbg) ")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::read_program;
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    fn script(commands: &str) -> String {
+        let set = InstructionSet::handheld();
+        let mut debugger = Debugger::new(read_program(EXAMPLE), &set);
+        let mut output = Vec::new();
+        run(&mut debugger, commands.as_bytes(), &mut output, false).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_step_and_trace() {
+        let output = script("step 2\ntrace 1\n");
+        assert_eq!(
+            output,
+            "pc 0: nop +0\tacc 0\n\
+             #1     pc    0: nop +0\n\
+             #2     pc    1: acc +1\tacc 0 -> 1\n\
+             pc 2: jmp +4\tacc 1\n\
+             #2     pc    1: acc +1\tacc 0 -> 1\n"
+        );
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let output = script("break jmp\ncontinue\ncontinue\ndelete jmp\nbreak 3\nc\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "breakpoint set at jmp");
+        assert_eq!(lines[2], "breakpoint at jmp");
+        assert_eq!(lines[3], "pc 2: jmp +4\tacc 1");
+        assert_eq!(lines[5], "pc 7: jmp -4\tacc 2");
+        assert_eq!(lines[8], "breakpoint at pc 3");
+        assert_eq!(lines[9], "pc 3: acc +3\tacc 2");
+    }
+
+    #[test]
+    fn test_watch_and_loop() {
+        let output = script("watch acc\nc\nc\nc\nc\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[2], "watchpoint: acc 0 -> 1");
+        assert_eq!(lines[4], "watchpoint: acc 1 -> 2");
+        assert_eq!(lines[6], "watchpoint: acc 2 -> 5");
+        assert_eq!(lines[8], "halted: loop detected at pc 1");
+    }
+
+    #[test]
+    fn test_patch_makes_program_terminate() {
+        let output = script("patch 7 nop -4\nc\nquit\nprint\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "pc 7: jmp -4 -> nop -4");
+        assert_eq!(lines[2], "halted: terminated");
+        assert_eq!(lines[3], "pc 9: <end>\tacc 8");
+        assert_eq!(lines.len(), 4); // nothing after quit
+    }
+
+    #[test]
+    fn test_errors() {
+        let output = script("break foo\nstep x\npatch 99 nop +0\nfly\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "error: unknown pc or opcode 'foo'");
+        assert_eq!(lines[2], "error: invalid count 'x'");
+        assert_eq!(lines[3], "error: pc 99 is outside the program");
+        assert_eq!(lines[4], "error: unknown command 'fly'");
+    }
+}
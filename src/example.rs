@@ -2,8 +2,7 @@
 // (source: aoc 2019 day 1 )
 
 use crate::util::read_lines;
-use crate::util::{print_part_1, print_part_2};
-use std::time::Instant;
+use crate::util::{print_part_1, print_part_2, start_part};
 
 fn calculate_fuel(mass: usize) -> usize {
     // can be checked with a checked_sub(2).unwrap_or(0) too!
@@ -56,7 +55,7 @@ pub fn main() {
         }
     };
     // PART 1
-    let start = Instant::now();
+    let start = start_part();
     let known_answer = "3330521";
     let part_1: usize = input.iter().map(|&i| calculate_fuel(i)).sum();
     let duration = start.elapsed();
@@ -64,7 +63,7 @@ pub fn main() {
 
     // PART 2
     let known_answer = "4992931";
-    let start = Instant::now();
+    let start = start_part();
     let part_2: usize = input.iter().map(|&i| recurse_fuel(i)).sum();
     let duration = start.elapsed();
     print_part_2(&part_2.to_string(), known_answer, duration);
@@ -0,0 +1,673 @@
+// Context-free grammars in the rule notation of day 19, recognized with an Earley parser.
+//   0: 8 11
+//   8: 42 | 42 8
+//   42: "a" | "bc" x
+// A rule has alternatives separated by '|', each a sequence of rule names and string
+// literals of any length. Any recursion is allowed, `""` or an empty alternative matches
+// nothing.
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Symbol {
+    Rule(usize),
+    Literal(String),
+}
+
+type Alternatives = Vec<Vec<Symbol>>;
+
+#[derive(Clone)]
+pub struct Grammar {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    rules: Vec<Option<Alternatives>>, // None when a rule is referenced but not (yet) defined
+    nullable: Vec<bool>,              // whether a rule can match the empty string
+}
+
+fn parse_alternatives(
+    body: &str,
+    mut rule: impl FnMut(&str) -> usize,
+) -> Result<Alternatives, String> {
+    let mut alternatives = vec![Vec::new()];
+    let mut chars = body.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            ' ' | '\t' => {}
+            '|' => alternatives.push(Vec::new()),
+            '"' => {
+                let end = body[start + 1..]
+                    .find('"')
+                    .ok_or("unterminated string literal")?;
+                let literal = &body[start + 1..start + 1 + end];
+                alternatives
+                    .last_mut()
+                    .unwrap()
+                    .push(Symbol::Literal(literal.to_string()));
+                while chars.next_if(|&(i, _)| i <= start + 1 + end).is_some() {}
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '|' || c == '"' {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let symbol = Symbol::Rule(rule(&body[start..end]));
+                alternatives.last_mut().unwrap().push(symbol);
+            }
+        }
+    }
+    Ok(alternatives)
+}
+
+impl Grammar {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut grammar = Grammar {
+            names: Vec::new(),
+            index: HashMap::new(),
+            rules: Vec::new(),
+            nullable: Vec::new(),
+        };
+        grammar.define(text, false)?;
+        Ok(grammar)
+    }
+
+    /// Replaces the rules defined in `text`, e.g. to introduce loops.
+    /// Leaves the grammar as it was when any line of the patch is invalid.
+    pub fn patch(&mut self, text: &str) -> Result<(), String> {
+        let mut patched = self.clone();
+        patched.define(text, true)?;
+        *self = patched;
+        Ok(())
+    }
+
+    fn define(&mut self, text: &str, replace: bool) -> Result<(), String> {
+        let mut defined = HashSet::new();
+        for (nr, line) in text.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", nr + 1, message);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let colon = line
+                .find(':')
+                .ok_or_else(|| error("expected ':'".to_string()))?;
+            let name = line[..colon].trim();
+            if name.is_empty() || name.contains(char::is_whitespace) || name.contains('"') {
+                return Err(error(format!("invalid rule name '{}'", name)));
+            }
+            let index = self.intern(name);
+            if !defined.insert(index) || (!replace && self.rules[index].is_some()) {
+                return Err(error(format!("rule {} is defined twice", name)));
+            }
+            let alternatives =
+                parse_alternatives(&line[colon + 1..], |name| self.intern(name)).map_err(error)?;
+            self.rules[index] = Some(alternatives);
+        }
+        if let Some(undefined) = self.rules.iter().position(Option::is_none) {
+            return Err(format!("rule {} is never defined", self.names[undefined]));
+        }
+        self.nullable = self.find_nullable();
+        Ok(())
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.index.get(name) {
+            return index;
+        }
+        self.names.push(name.to_string());
+        self.index.insert(name.to_string(), self.rules.len());
+        self.rules.push(None);
+        self.rules.len() - 1
+    }
+
+    pub fn rule(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    pub fn name(&self, rule: usize) -> &str {
+        &self.names[rule]
+    }
+
+    pub fn alternatives(&self, rule: usize) -> &[Vec<Symbol>] {
+        self.rules[rule].as_deref().unwrap_or_default() // every rule is defined after parsing
+    }
+
+    fn find_nullable(&self) -> Vec<bool> {
+        let mut nullable = vec![false; self.rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in 0..self.rules.len() {
+                if !nullable[rule]
+                    && self.alternatives(rule).iter().any(|alt| {
+                        alt.iter().all(|symbol| match symbol {
+                            Symbol::Rule(r) => nullable[*r],
+                            Symbol::Literal(literal) => literal.is_empty(),
+                        })
+                    })
+                {
+                    nullable[rule] = true;
+                    changed = true;
+                }
+            }
+        }
+        nullable
+    }
+
+    /// Whether all of `message` is derived from the rule `start`.
+    pub fn matches(&self, start: usize, message: &str) -> bool {
+        Chart::parse(self, start, message).accepts()
+    }
+
+    /// Whether any rule reachable from `start` (`start` included) derives itself, directly or
+    /// through others, e.g. `0: 8 11` with a recursive `8: 42 | 42 8`.
+    pub fn is_recursive(&self, start: usize) -> bool {
+        // depth-first search for a rule that is still on the stack
+        fn visit(grammar: &Grammar, rule: usize, state: &mut Vec<u8>) -> bool {
+            match state[rule] {
+                1 => return true,  // on the stack
+                2 => return false, // done
+                _ => {}
+            }
+            state[rule] = 1;
+            for symbol in grammar.alternatives(rule).iter().flatten() {
+                if let Symbol::Rule(next) = symbol {
+                    if visit(grammar, *next, state) {
+                        return true;
+                    }
+                }
+            }
+            state[rule] = 2;
+            false
+        }
+        visit(self, start, &mut vec![0; self.rules.len()])
+    }
+
+    /// A regular expression matching exactly the messages derived from `start`, None when
+    /// the rule is recursive (or the expression would be too large).
+    pub fn to_regex(&self, start: usize) -> Option<Regex> {
+        if self.is_recursive(start) {
+            return None;
+        }
+        let mut patterns = vec![None; self.rules.len()];
+        let pattern = self.pattern(start, &mut patterns);
+        Regex::new(&format!("^{}$", pattern)).ok()
+    }
+
+    fn pattern(&self, rule: usize, patterns: &mut Vec<Option<String>>) -> String {
+        if let Some(pattern) = &patterns[rule] {
+            return pattern.clone();
+        }
+        let alternatives: Vec<String> = self
+            .alternatives(rule)
+            .iter()
+            .map(|alt| {
+                alt.iter()
+                    .map(|symbol| match symbol {
+                        Symbol::Rule(rule) => self.pattern(*rule, patterns),
+                        Symbol::Literal(literal) => regex::escape(literal),
+                    })
+                    .collect()
+            })
+            .collect();
+        let pattern = format!("(?:{})", alternatives.join("|"));
+        patterns[rule] = Some(pattern.clone());
+        pattern
+    }
+
+    /// A matcher for `start`: a compiled regex when possible, the Earley parser otherwise.
+    pub fn matcher(&self, start: usize) -> Matcher<'_> {
+        match self.to_regex(start) {
+            Some(regex) => Matcher::Regex(regex),
+            None => Matcher::Earley {
+                grammar: self,
+                start,
+            },
+        }
+    }
+
+    /// How `message` is derived from the rule `start`, or where that goes wrong.
+    pub fn explain(&self, start: usize, message: &str) -> Result<Tree, Failure> {
+        let chart = Chart::parse(self, start, message);
+        chart.tree().ok_or_else(|| chart.failure())
+    }
+}
+
+pub enum Matcher<'a> {
+    Regex(Regex),
+    Earley { grammar: &'a Grammar, start: usize },
+}
+
+impl Matcher<'_> {
+    pub fn matches(&self, message: &str) -> bool {
+        match self {
+            Matcher::Regex(regex) => regex.is_match(message),
+            Matcher::Earley { grammar, start } => grammar.matches(*start, message),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Tree {
+    Rule {
+        rule: usize,
+        span: (usize, usize), // the bytes of the message matched by the rule
+        children: Vec<Tree>,
+    },
+    Literal(String),
+}
+
+impl Tree {
+    /// One node per line, children indented below their rule with the text the rule matched.
+    pub fn indented(&self, grammar: &Grammar, message: &str) -> String {
+        let mut lines = String::new();
+        self.indent_into(&mut lines, 0, grammar, message);
+        lines
+    }
+
+    fn indent_into(&self, lines: &mut String, depth: usize, grammar: &Grammar, message: &str) {
+        let indent = "  ".repeat(depth);
+        match self {
+            Tree::Rule {
+                rule,
+                span: (start, end),
+                children,
+            } => {
+                lines.push_str(&format!(
+                    "{}{} {:?}\n",
+                    indent,
+                    grammar.name(*rule),
+                    &message[*start..*end]
+                ));
+                for child in children {
+                    child.indent_into(lines, depth + 1, grammar, message);
+                }
+            }
+            Tree::Literal(literal) => lines.push_str(&format!("{}{:?}\n", indent, literal)),
+        }
+    }
+
+    /// Graphviz representation, rules as ellipses and literals as boxes.
+    pub fn to_dot(&self, grammar: &Grammar) -> String {
+        let mut dot = String::from("digraph derivation {\n    ordering=out;\n");
+        self.dot_into(&mut dot, &mut 0, grammar);
+        dot.push_str("}\n");
+        dot
+    }
+
+    // returns the id of the node
+    fn dot_into(&self, dot: &mut String, next_id: &mut usize, grammar: &Grammar) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        match self {
+            Tree::Rule { rule, children, .. } => {
+                dot.push_str(&format!("    n{} [label={:?}];\n", id, grammar.name(*rule)));
+                for child in children {
+                    let child_id = child.dot_into(dot, next_id, grammar);
+                    dot.push_str(&format!("    n{} -> n{};\n", id, child_id));
+                }
+            }
+            Tree::Literal(literal) => dot.push_str(&format!(
+                "    n{} [label={:?}, shape=box];\n",
+                id,
+                format!("{:?}", literal)
+            )),
+        }
+        id
+    }
+}
+
+/// Why a message does not match: the longest prefix that can still be extended to a match,
+/// with the literals the rules expected right after it.
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub matched: usize,
+    pub expected: Vec<(usize, String)>, // (rule, literal), empty when only the end was allowed
+}
+
+/// Rule `rule`, alternative `alt`, with the first `dot` symbols matched from position `origin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// Earley chart: the items in every set `i` end at byte `i` of the message.
+struct Chart<'a> {
+    grammar: &'a Grammar,
+    start: usize,
+    message: &'a str,
+    sets: Vec<Vec<Item>>,
+}
+
+impl<'a> Chart<'a> {
+    fn parse(grammar: &'a Grammar, start: usize, message: &'a str) -> Self {
+        let len = message.len();
+        let mut sets: Vec<Vec<Item>> = vec![Vec::new(); len + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); len + 1];
+        let mut add = |sets: &mut Vec<Vec<Item>>, pos: usize, item: Item| {
+            if seen[pos].insert(item) {
+                sets[pos].push(item);
+            }
+        };
+        for alt in 0..grammar.alternatives(start).len() {
+            let item = Item {
+                rule: start,
+                alt,
+                dot: 0,
+                origin: 0,
+            };
+            add(&mut sets, 0, item);
+        }
+
+        for pos in 0..=len {
+            let mut i = 0;
+            while i < sets[pos].len() {
+                let item = sets[pos][i];
+                i += 1;
+                let advanced = Item {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match grammar.alternatives(item.rule)[item.alt].get(item.dot) {
+                    // complete: advance the items waiting for this rule
+                    None => {
+                        let mut j = 0;
+                        while j < sets[item.origin].len() {
+                            let waiting = sets[item.origin][j];
+                            j += 1;
+                            let alt = &grammar.alternatives(waiting.rule)[waiting.alt];
+                            if alt.get(waiting.dot) == Some(&Symbol::Rule(item.rule)) {
+                                let waiting = Item {
+                                    dot: waiting.dot + 1,
+                                    ..waiting
+                                };
+                                add(&mut sets, pos, waiting);
+                            }
+                        }
+                    }
+                    Some(Symbol::Rule(rule)) => {
+                        for alt in 0..grammar.alternatives(*rule).len() {
+                            let predicted = Item {
+                                rule: *rule,
+                                alt,
+                                dot: 0,
+                                origin: pos,
+                            };
+                            add(&mut sets, pos, predicted);
+                        }
+                        // a nullable rule may be completed already, skip over it right away
+                        if grammar.nullable[*rule] {
+                            add(&mut sets, pos, advanced);
+                        }
+                    }
+                    Some(Symbol::Literal(literal)) => {
+                        if message[pos..].starts_with(literal.as_str()) {
+                            add(&mut sets, pos + literal.len(), advanced);
+                        }
+                    }
+                }
+            }
+        }
+        Chart {
+            grammar,
+            start,
+            message,
+            sets,
+        }
+    }
+
+    fn is_complete(&self, item: &Item) -> bool {
+        item.dot == self.grammar.alternatives(item.rule)[item.alt].len()
+    }
+
+    fn accepts(&self) -> bool {
+        self.sets[self.message.len()]
+            .iter()
+            .any(|item| item.rule == self.start && item.origin == 0 && self.is_complete(item))
+    }
+
+    fn tree(&self) -> Option<Tree> {
+        if !self.accepts() {
+            return None;
+        }
+        // (rule, start, end) of every rule that matched part of the message
+        let mut completed = HashSet::new();
+        for (end, set) in self.sets.iter().enumerate() {
+            for item in set.iter().filter(|item| self.is_complete(item)) {
+                completed.insert((item.rule, item.origin, end));
+            }
+        }
+        self.build(
+            self.start,
+            (0, self.message.len()),
+            &completed,
+            &mut HashSet::new(),
+        )
+    }
+
+    // `building` holds the rules being built above this one: a rule that derives itself
+    // over the same span (through nullable rules) is never needed, only makes it loop
+    fn build(
+        &self,
+        rule: usize,
+        span: (usize, usize),
+        completed: &HashSet<(usize, usize, usize)>,
+        building: &mut HashSet<(usize, (usize, usize))>,
+    ) -> Option<Tree> {
+        if !building.insert((rule, span)) {
+            return None;
+        }
+        let tree = self.grammar.alternatives(rule).iter().find_map(|alt| {
+            let children = self.build_sequence(alt, span, completed, building)?;
+            Some(Tree::Rule {
+                rule,
+                span,
+                children,
+            })
+        });
+        building.remove(&(rule, span));
+        tree
+    }
+
+    fn build_sequence(
+        &self,
+        symbols: &[Symbol],
+        (start, end): (usize, usize),
+        completed: &HashSet<(usize, usize, usize)>,
+        building: &mut HashSet<(usize, (usize, usize))>,
+    ) -> Option<Vec<Tree>> {
+        let (first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None if start == end => return Some(Vec::new()),
+            None => return None,
+        };
+        let (first, mid) = match first {
+            Symbol::Literal(literal) => {
+                if !self.message[start..end].starts_with(literal.as_str()) {
+                    return None;
+                }
+                (Tree::Literal(literal.clone()), start + literal.len())
+            }
+            Symbol::Rule(rule) => (start..=end)
+                .filter(|&mid| completed.contains(&(*rule, start, mid)))
+                .find_map(|mid| {
+                    // the rest is checked again below, but stops hopeless splits early
+                    self.build_sequence(rest, (mid, end), completed, building)?;
+                    Some((self.build(*rule, (start, mid), completed, building)?, mid))
+                })?,
+        };
+        let mut children = vec![first];
+        children.extend(self.build_sequence(rest, (mid, end), completed, building)?);
+        Some(children)
+    }
+
+    fn failure(&self) -> Failure {
+        let matched = (0..=self.message.len())
+            .rev()
+            .find(|&pos| !self.sets[pos].is_empty())
+            .unwrap_or(0);
+        let mut expected = Vec::new();
+        for item in self.sets[matched].iter() {
+            if let Some(Symbol::Literal(literal)) =
+                self.grammar.alternatives(item.rule)[item.alt].get(item.dot)
+            {
+                if !expected.contains(&(item.rule, literal.clone())) {
+                    expected.push((item.rule, literal.clone()));
+                }
+            }
+        }
+        Failure { matched, expected }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(rules: &str, message: &str) -> bool {
+        let grammar = Grammar::parse(rules).unwrap();
+        grammar.matches(grammar.rule("0").unwrap(), message)
+    }
+
+    #[test]
+    fn test_literals_and_recursion() {
+        let rules = "0: 1 \"--\" 2\n1: \"ab\" | 1 \"ab\"\n2: \"x\" 2 \"y\" | \"\"";
+        assert!(matches(rules, "ab--"));
+        assert!(matches(rules, "ababab--xxyy"));
+        assert!(!matches(rules, "abab--xxy"));
+        assert!(!matches(rules, "--xy"));
+        assert!(!matches(rules, "aba--"));
+
+        // left, right and middle recursion, nullable rules
+        let rules = "0: 0 0 | \"(\" 0 \")\" |\n";
+        assert!(matches(rules, ""));
+        assert!(matches(rules, "(()())(())"));
+        assert!(!matches(rules, "(()"));
+        let rules = "0: 1 1 \"z\"\n1: | 1";
+        assert!(matches(rules, "z"));
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut grammar = Grammar::parse("0: 8\n8: 42\n42: \"a\"").unwrap();
+        let start = grammar.rule("0").unwrap();
+        assert!(!grammar.matches(start, "aaa"));
+        grammar.patch("8: 42 | 42 8").unwrap();
+        assert!(grammar.matches(start, "aaa"));
+        let before = grammar.alternatives(grammar.rule("8").unwrap()).to_vec();
+        assert_eq!(
+            grammar.patch("8: 42 9").unwrap_err(),
+            "rule 9 is never defined"
+        );
+        assert_eq!(
+            grammar.patch("8: 42\n42: \"a").unwrap_err(),
+            "line 2: unterminated string literal"
+        );
+        // a failed patch changes nothing
+        assert_eq!(grammar.rule("9"), None);
+        assert_eq!(
+            grammar.alternatives(grammar.rule("8").unwrap()),
+            &before[..]
+        );
+        assert!(grammar.matches(start, "aaa"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |rules| Grammar::parse(rules).err().unwrap();
+        assert_eq!(error("0: 1\n1: \"a"), "line 2: unterminated string literal");
+        assert_eq!(error("0: 1 2\n1: \"a\""), "rule 2 is never defined");
+        assert_eq!(
+            error("0: \"a\"\n0: \"b\""),
+            "line 2: rule 0 is defined twice"
+        );
+        assert_eq!(error("0 \"a\""), "line 1: expected ':'");
+    }
+
+    #[test]
+    fn test_explain() {
+        let grammar = Grammar::parse(
+            "0: 4 1 5\n1: 2 3 | 3 2\n2: 4 4 | 5 5\n3: 4 5 | 5 4\n4: \"a\"\n5: \"b\"",
+        )
+        .unwrap();
+        let start = grammar.rule("0").unwrap();
+        let tree = grammar.explain(start, "ababbb").unwrap();
+        assert_eq!(
+            tree.indented(&grammar, "ababbb"),
+            "0 \"ababbb\"\n  4 \"a\"\n    \"a\"\n  1 \"babb\"\n    3 \"ba\"\n      5 \"b\"\n        \"b\"\n      4 \"a\"\n        \"a\"\n    2 \"bb\"\n      5 \"b\"\n        \"b\"\n      5 \"b\"\n        \"b\"\n  5 \"b\"\n    \"b\"\n"
+        );
+        let dot = tree.to_dot(&grammar);
+        assert!(dot.starts_with("digraph derivation {"));
+        assert!(dot.contains("    n0 [label=\"0\"];\n    n1 [label=\"4\"];\n    n2 [label=\"\\\"a\\\"\", shape=box];\n    n1 -> n2;\n"));
+
+        let a = (grammar.rule("4").unwrap(), "a".to_string());
+        let failure = grammar.explain(start, "bababa").unwrap_err();
+        assert_eq!(
+            failure,
+            Failure {
+                matched: 0,
+                expected: vec![a.clone()]
+            }
+        );
+        let failure = grammar.explain(start, "aaabbb").unwrap_err();
+        assert_eq!(
+            failure,
+            Failure {
+                matched: 4,
+                expected: vec![a]
+            }
+        );
+        let failure = grammar.explain(start, "ababbbb").unwrap_err();
+        assert_eq!(
+            failure,
+            Failure {
+                matched: 6,
+                expected: vec![]
+            }
+        );
+        let failure = grammar.explain(start, "aab").unwrap_err();
+        assert_eq!(failure.matched, 3);
+        assert_eq!(failure.expected.len(), 2);
+    }
+
+    #[test]
+    fn test_explain_nullable_cycles() {
+        let grammar = Grammar::parse("0: 0 0 | \"(\" 0 \")\" |").unwrap();
+        let tree = grammar.explain(0, "()").unwrap();
+        assert_eq!(
+            tree.indented(&grammar, "()"),
+            "0 \"()\"\n  \"(\"\n  0 \"\"\n  \")\"\n"
+        );
+    }
+
+    #[test]
+    fn test_matcher() {
+        let rules = "0: 1 \"--\" 2\n1: \"a.b\" | 2 \"(\"\n2: \"x\" | \"\"";
+        let grammar = Grammar::parse(rules).unwrap();
+        assert!(!grammar.is_recursive(0));
+        let regex = grammar.to_regex(0).unwrap();
+        assert_eq!(regex.as_str(), "^(?:(?:a\\.b|(?:x|)\\()\\-\\-(?:x|))$");
+        let matcher = grammar.matcher(0);
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        for message in ["a.b--x", "(--", "x(--x", "axb--", "a.b--xx", ""].iter() {
+            assert_eq!(
+                matcher.matches(message),
+                grammar.matches(0, message),
+                "{}",
+                message
+            );
+        }
+
+        let grammar = Grammar::parse("0: 1 2\n1: \"a\"\n2: \"b\" | 1 2").unwrap();
+        assert!(grammar.is_recursive(0));
+        assert!(!grammar.is_recursive(1));
+        assert!(matches!(grammar.matcher(0), Matcher::Earley { .. }));
+        assert!(grammar.matcher(0).matches("aaab"));
+    }
+}
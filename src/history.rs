@@ -0,0 +1,365 @@
+// Persistent record of every run, so answers and timings can be compared across commits.
+// Stored as append-only JSON lines: one line per solved part.
+
+use crate::alloc::AllocStats;
+use crate::util::PartResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{self, Write};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Record {
+    pub commit: String,
+    pub dirty: bool,
+    pub run: u64, // unix timestamp (ms) of the start of the run, shared by all its parts
+    pub day: usize,
+    pub part: usize,
+    pub answer: String,
+    pub correct: bool,
+    pub nanos: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<AllocStats>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Run {
+    commit: String,
+    dirty: bool,
+    started: u64,
+}
+
+impl Run {
+    pub fn start() -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let commit =
+            git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+        let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+            .map(|status| !status.is_empty())
+            .unwrap_or(false);
+        Run {
+            commit,
+            dirty,
+            started,
+        }
+    }
+
+    pub fn records(&self, day: usize, results: &[PartResult]) -> Vec<Record> {
+        results
+            .iter()
+            .map(|result| Record {
+                commit: self.commit.clone(),
+                dirty: self.dirty,
+                run: self.started,
+                day,
+                part: result.part,
+                answer: result.answer.clone(),
+                correct: result.correct(),
+                nanos: result.duration.as_nanos() as u64,
+                memory: result.memory,
+            })
+            .collect()
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Resolves a revision like `HEAD~1` to the short hash used in the history file.
+/// Anything git does not know about is used as-is (e.g. a hash from a deleted branch).
+pub fn resolve_rev(rev: &str) -> String {
+    git(&["rev-parse", "--short", rev]).unwrap_or_else(|| rev.to_string())
+}
+
+pub fn append(path: &str, records: &[Record]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    Ok(())
+}
+
+pub fn load(path: &str) -> io::Result<Vec<Record>> {
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    for (nr, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) => eprintln!("Skipping line {} of {}: {}", nr + 1, path, e),
+        }
+    }
+    Ok(records)
+}
+
+/// The one recorded commit whose hash starts with `rev`, ignoring runs of a dirty worktree:
+/// those are stored under HEAD's hash but did not run HEAD's code.
+fn find_commit<'a>(records: &'a [Record], rev: &str) -> Result<&'a str, String> {
+    let commits: BTreeSet<&str> = records
+        .iter()
+        .filter(|r| !r.dirty && r.commit.starts_with(rev))
+        .map(|r| r.commit.as_str())
+        .collect();
+    match commits.len() {
+        0 => Err(format!("No clean runs recorded for {}", rev)),
+        1 => Ok(commits.into_iter().next().unwrap()),
+        _ => Err(format!(
+            "{} matches several commits: {}",
+            rev,
+            commits.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+pub fn print_history(records: &[Record], day: Option<usize>) {
+    // (run, commit) -> (dirty, parts, incorrect, total nanos)
+    let mut runs: BTreeMap<(u64, &str), (bool, usize, usize, u64)> = BTreeMap::new();
+    for record in records.iter().filter(|r| day.is_none_or(|d| r.day == d)) {
+        let entry = runs
+            .entry((record.run, &record.commit))
+            .or_insert((record.dirty, 0, 0, 0));
+        entry.1 += 1;
+        if !record.correct {
+            entry.2 += 1;
+        }
+        entry.3 += record.nanos;
+    }
+    if runs.is_empty() {
+        println!("No runs recorded yet..");
+        return;
+    }
+    println!(
+        "{:>14} | {:<14} | {:>5} | {:>9} | {:>14}",
+        "RUN", "COMMIT", "PARTS", "INCORRECT", "TOTAL TIME"
+    );
+    for ((run, commit), (dirty, parts, incorrect, nanos)) in runs {
+        let commit = if dirty {
+            format!("{}+dirty", commit)
+        } else {
+            commit.to_string()
+        };
+        println!(
+            "{:>14} | {:<14} | {:>5} | {:>9} | {:>14}",
+            run,
+            commit,
+            parts,
+            incorrect,
+            format!("{:?}", std::time::Duration::from_nanos(nanos))
+        );
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Answer { old: String, new: String },
+    Regression { old_nanos: u64, new_nanos: u64 },
+    Missing,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Difference {
+    pub day: usize,
+    pub part: usize,
+    pub change: Change,
+}
+
+// fastest clean run per (day, part) of the given commit, the least noisy measurement available
+fn best_per_part<'a>(records: &'a [Record], commit: &str) -> BTreeMap<(usize, usize), &'a Record> {
+    let mut best: BTreeMap<(usize, usize), &Record> = BTreeMap::new();
+    for record in records.iter().filter(|r| !r.dirty && r.commit == commit) {
+        let entry = best.entry((record.day, record.part)).or_insert(record);
+        if record.nanos < entry.nanos {
+            *entry = record;
+        }
+    }
+    best
+}
+
+/// Lists every part whose answer changed between `old_rev` and `new_rev`, or whose best time
+/// got slower by more than `threshold` (0.1 == 10%). Runs of a dirty worktree are left out.
+pub fn compare(
+    records: &[Record],
+    old_rev: &str,
+    new_rev: &str,
+    threshold: f64,
+) -> Result<Vec<Difference>, String> {
+    let old = best_per_part(records, find_commit(records, old_rev)?);
+    let new = best_per_part(records, find_commit(records, new_rev)?);
+
+    let mut differences = Vec::new();
+    for (&(day, part), old_record) in old.iter() {
+        let change = match new.get(&(day, part)) {
+            None => Some(Change::Missing),
+            Some(new_record) if new_record.answer != old_record.answer => Some(Change::Answer {
+                old: old_record.answer.clone(),
+                new: new_record.answer.clone(),
+            }),
+            Some(new_record)
+                if new_record.nanos as f64 > old_record.nanos as f64 * (1.0 + threshold) =>
+            {
+                Some(Change::Regression {
+                    old_nanos: old_record.nanos,
+                    new_nanos: new_record.nanos,
+                })
+            }
+            _ => None,
+        };
+        if let Some(change) = change {
+            differences.push(Difference { day, part, change });
+        }
+    }
+    Ok(differences)
+}
+
+pub fn print_comparison(records: &[Record], old_rev: &str, new_rev: &str, threshold: f64) {
+    let old_rev = resolve_rev(old_rev);
+    let new_rev = resolve_rev(new_rev);
+    let differences = match compare(records, &old_rev, &new_rev, threshold) {
+        Ok(differences) => differences,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if differences.is_empty() {
+        println!(
+            "No answer changes or regressions above {:.0}% between {} and {}",
+            threshold * 100.0,
+            old_rev,
+            new_rev
+        );
+        return;
+    }
+    for diff in differences {
+        match diff.change {
+            Change::Answer { old, new } => println!(
+                "DAY {} PART {}: ANSWER CHANGED {} -> {}",
+                diff.day, diff.part, old, new
+            ),
+            Change::Regression {
+                old_nanos,
+                new_nanos,
+            } => println!(
+                "DAY {} PART {}: SLOWER {:?} -> {:?} (+{:.1}%)",
+                diff.day,
+                diff.part,
+                std::time::Duration::from_nanos(old_nanos),
+                std::time::Duration::from_nanos(new_nanos),
+                (new_nanos as f64 / old_nanos as f64 - 1.0) * 100.0
+            ),
+            Change::Missing => println!(
+                "DAY {} PART {}: NOT RUN at {}",
+                diff.day, diff.part, new_rev
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(commit: &str, day: usize, part: usize, answer: &str, nanos: u64) -> Record {
+        Record {
+            commit: commit.to_string(),
+            dirty: false,
+            run: 0,
+            day,
+            part,
+            answer: answer.to_string(),
+            correct: true,
+            nanos,
+            memory: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_answer_change() {
+        let records = vec![
+            record("aaa", 1, 1, "42", 100),
+            record("bbb", 1, 1, "43", 100),
+        ];
+        let differences = compare(&records, "aaa", "bbb", 0.1).unwrap();
+        assert_eq!(
+            differences,
+            vec![Difference {
+                day: 1,
+                part: 1,
+                change: Change::Answer {
+                    old: "42".to_string(),
+                    new: "43".to_string()
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compare_regression_threshold() {
+        let records = vec![
+            record("aaa", 1, 1, "42", 100),
+            record("aaa", 1, 2, "7", 100),
+            record("bbb", 1, 1, "42", 105),
+            record("bbb", 1, 2, "7", 200),
+            record("bbb", 1, 2, "7", 150), // fastest run counts
+        ];
+        let differences = compare(&records, "aaa", "bbb", 0.1).unwrap();
+        assert_eq!(
+            differences,
+            vec![Difference {
+                day: 1,
+                part: 2,
+                change: Change::Regression {
+                    old_nanos: 100,
+                    new_nanos: 150
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compare_short_hashes() {
+        let records = vec![
+            record("aaaaaaa", 2, 1, "1", 100),
+            record("abbbbbb", 2, 1, "1", 100),
+            record("ccccccc", 2, 1, "1", 100),
+        ];
+        assert_eq!(compare(&records, "aaaa", "ab", 0.1), Ok(vec![]));
+        // the stored hash has to start with the revision, not the other way around
+        assert!(compare(&records, "aaaaaaa1", "ab", 0.1).is_err());
+        assert!(compare(&records, "a", "ccc", 0.1).is_err());
+        assert!(compare(&records, "aaaa", "ddd", 0.1).is_err());
+    }
+
+    #[test]
+    fn test_compare_skips_dirty_runs() {
+        let mut dirty = record("bbb", 1, 1, "43", 50);
+        dirty.dirty = true;
+        let records = vec![
+            record("aaa", 1, 1, "42", 100),
+            record("bbb", 1, 1, "42", 100),
+            dirty,
+        ];
+        assert_eq!(compare(&records, "aaa", "bbb", 0.1), Ok(vec![]));
+
+        let records = vec![record("aaa", 1, 1, "42", 100), records[2].clone()];
+        assert!(compare(&records, "aaa", "bbb", 0.1).is_err());
+    }
+}
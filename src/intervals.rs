@@ -0,0 +1,104 @@
+// Sets of integers stored as sorted, disjoint inclusive ranges.
+
+use std::iter::FromIterator;
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct IntervalSet {
+    ranges: Vec<(usize, usize)>, // sorted, neither overlapping nor touching
+}
+
+impl IntervalSet {
+    #[cfg(test)]
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        // the last range that starts at or before the value
+        let i = self.ranges.partition_point(|&(lower, _)| lower <= value);
+        i > 0 && value <= self.ranges[i - 1].1
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        self.ranges
+            .iter()
+            .chain(other.ranges.iter())
+            .copied()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (self.ranges[i], other.ranges[j]);
+            let (lower, upper) = (a.0.max(b.0), a.1.min(b.1));
+            if lower <= upper {
+                ranges.push((lower, upper));
+            }
+            // the range that ends first cannot overlap anything further on
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+/// Merges the ranges, which may be in any order and overlap. Empty ranges (lower > upper) are ignored.
+impl FromIterator<(usize, usize)> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = (usize, usize)>>(iter: I) -> Self {
+        let mut sorted: Vec<(usize, usize)> = iter
+            .into_iter()
+            .filter(|(lower, upper)| lower <= upper)
+            .collect();
+        sorted.sort_unstable();
+        let mut ranges: Vec<(usize, usize)> = Vec::with_capacity(sorted.len());
+        for (lower, upper) in sorted {
+            match ranges.last_mut() {
+                Some(last) if lower <= last.1.saturating_add(1) => last.1 = last.1.max(upper),
+                _ => ranges.push((lower, upper)),
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(usize, usize)]) -> IntervalSet {
+        ranges.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            set(&[(10, 12), (1, 3), (2, 5), (6, 6), (20, 19)]).ranges(),
+            &[(1, 6), (10, 12)]
+        );
+        assert_eq!(set(&[(5, usize::MAX), (0, 4)]).ranges(), &[(0, usize::MAX)]);
+        assert_eq!(set(&[]).ranges(), &[]);
+    }
+
+    #[test]
+    fn test_contains() {
+        let numbers = set(&[(1, 3), (5, 7), (10, 10)]);
+        let contained: Vec<usize> = (0..12).filter(|&n| numbers.contains(n)).collect();
+        assert_eq!(contained, vec![1, 2, 3, 5, 6, 7, 10]);
+        assert!(!set(&[]).contains(0));
+    }
+
+    #[test]
+    fn test_union_intersection() {
+        let a = set(&[(1, 3), (5, 7), (10, 20)]);
+        let b = set(&[(3, 5), (8, 8), (15, 30)]);
+        assert_eq!(a.union(&b).ranges(), &[(1, 8), (10, 30)]);
+        assert_eq!(a.intersection(&b).ranges(), &[(3, 3), (5, 5), (15, 20)]);
+        assert_eq!(a.intersection(&set(&[])).ranges(), &[]);
+        assert_eq!(a.intersection(&a), a);
+    }
+}
@@ -1,5 +1,10 @@
+use std::fs::{self, read_to_string};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use structopt::StructOpt;
 
+mod alloc;
 mod util; // https://users.rust-lang.org/t/modules-what-am-i-doing-wrong/35186/2
 
 mod day1;
@@ -31,6 +36,24 @@ mod day9;
 mod example;
 mod infi;
 
+mod answers;
+mod asm;
+mod cfg;
+mod client;
+mod debugger;
+mod grammar;
+mod history;
+mod intervals;
+mod matching;
+mod raster;
+mod scaffold;
+mod schema;
+mod vm;
+mod watch;
+
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
+
 // https://docs.rs/structopt/0.3.20/structopt/#how-to-derivestructopt
 #[derive(StructOpt)]
 #[structopt(
@@ -41,18 +64,214 @@ struct Opt {
     /// Specify day to run
     #[structopt(short = "d", long = "day", default_value = "all")]
     day: String,
+
+    /// Don't store answers and timings of this run in the history file
+    #[structopt(long = "no-history")]
+    no_history: bool,
+
+    /// Count allocations, allocated bytes and peak heap usage of every part
+    #[structopt(short = "m", long = "memory")]
+    memory: bool,
+
+    /// Print results as JSON lines instead of human readable text
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// Evaluate a day 18 expression with the operator precedence of both parts
+    #[structopt(long = "expr")]
+    expr: Option<String>,
+
+    /// Precedence table for --expr instead of those of the parts, e.g. "* > +, right-assoc ^"
+    #[structopt(long = "precedence")]
+    precedence: Option<String>,
+
+    /// Also print the --expr expression fully parenthesized
+    #[structopt(long = "parenthesize")]
+    parenthesize: bool,
+
+    /// Also print every step in the reduction of the --expr expression
+    #[structopt(long = "steps")]
+    steps: bool,
+
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+#[derive(StructOpt)]
+enum Cmd {
+    /// List all recorded runs (of the selected day)
+    History,
+    /// Flag answer changes and timing regressions between two recorded commits
+    Compare {
+        rev1: String,
+        rev2: String,
+        /// Allowed slowdown in percent before a part is reported as a regression
+        #[structopt(short = "t", long = "threshold", default_value = "10")]
+        threshold: f64,
+    },
+    /// Re-run the selected days whenever their files in inputs/ change, rebuild on changes in src/
+    Watch {
+        /// Polling interval in milliseconds
+        #[structopt(short = "i", long = "interval", default_value = "500")]
+        interval: u64,
+    },
+    /// Create the module, input, fixture and answers files of a new day and register it
+    New {
+        #[structopt(short = "d", long = "day")]
+        day: usize,
+    },
+    /// Download the input of the selected day(s), unless it is already there
+    Fetch {
+        /// Puzzle site to talk to, defaults to $AOC_BASE_URL or adventofcode.com
+        #[structopt(long = "base-url")]
+        base_url: Option<String>,
+    },
+    /// Submit an answer of the selected day, computing it when none is given
+    Submit {
+        #[structopt(short = "p", long = "part")]
+        part: usize,
+        answer: Option<String>,
+        /// Puzzle site to talk to, defaults to $AOC_BASE_URL or adventofcode.com
+        #[structopt(long = "base-url")]
+        base_url: Option<String>,
+    },
+    /// Assemble a day 8 program with labels and comments into the compact binary format
+    Asm {
+        source: String,
+        /// Defaults to the source with a .bin extension
+        #[structopt(short = "o", long = "output")]
+        output: Option<String>,
+    },
+    /// Print a day 8 program (.bin, .asm or puzzle notation) as assembly with labels
+    Disasm {
+        #[structopt(default_value = "inputs/day8.txt")]
+        program: String,
+    },
+    /// Print how a message derives from rule 0 of the day 19 rules, or where it stops matching
+    Explain {
+        message: String,
+        /// File with the rules, followed by an empty line and messages
+        #[structopt(long = "rules", default_value = "inputs/day19.txt")]
+        rules: String,
+        /// Patch the rules, like part 2 does
+        #[structopt(long = "part2")]
+        part2: bool,
+        /// Print the derivation tree in Graphviz DOT format instead of indented
+        #[structopt(long = "dot")]
+        dot: bool,
+    },
+    /// Find the sea monsters, or another pattern, in the assembled day 20 image
+    Monsters {
+        /// File with the tiles
+        #[structopt(long = "tiles", default_value = "inputs/day20.txt")]
+        tiles: String,
+        /// File with the pattern to look for: '#' must match, anything else may be anything
+        #[structopt(long = "monster")]
+        monster: Option<String>,
+    },
+    /// Draw the assembled day 20 tiles with their ids and the monsters in them
+    Render {
+        /// File with the tiles
+        #[structopt(long = "tiles", default_value = "inputs/day20.txt")]
+        tiles: String,
+        /// File with the pattern to look for, like for `monsters`
+        #[structopt(long = "monster")]
+        monster: Option<String>,
+        /// Write a .png or .ppm image, or text for any other extension, instead of printing text
+        #[structopt(short = "o", long = "output")]
+        output: Option<String>,
+        /// Size of a cell in pixels
+        #[structopt(long = "scale", default_value = "4")]
+        scale: usize,
+    },
+    /// Print which column every day 16 field is in, and whether that is the only possibility
+    Fields {
+        /// File with the rules, your ticket and nearby tickets
+        #[structopt(long = "notes", default_value = "inputs/day16.txt")]
+        notes: String,
+    },
+    /// Print the fields of your day 16 ticket by name
+    Ticket {
+        /// File with the rules, your ticket and nearby tickets
+        #[structopt(long = "notes", default_value = "inputs/day16.txt")]
+        notes: String,
+        /// Only the fields whose name matches, `*` matches any text
+        #[structopt(long = "fields", default_value = "*")]
+        fields: String,
+        /// Print the product of the values instead
+        #[structopt(long = "product")]
+        product: bool,
+    },
+    /// Print every day 16 nearby ticket value that fits no field, and the ranges it missed
+    Scan {
+        /// File with the rules, your ticket and nearby tickets
+        #[structopt(long = "notes", default_value = "inputs/day16.txt")]
+        notes: String,
+        /// Print the report as JSON
+        #[structopt(long = "json")]
+        json: bool,
+    },
+    /// Check a batch of day 4 passports against the puzzle's rules or a schema file
+    Passports {
+        /// File with passports separated by empty lines
+        #[structopt(long = "batch", default_value = "inputs/day4.txt")]
+        batch: String,
+        /// File with the rules for every field, see src/schema.rs for the format
+        #[structopt(long = "schema")]
+        schema: Option<String>,
+        /// Also print what is wrong with every invalid passport
+        #[structopt(long = "list")]
+        list: bool,
+    },
+    /// Print the control flow graph of a day 8 program in Graphviz DOT format
+    Cfg {
+        #[structopt(default_value = "inputs/day8.txt")]
+        program: String,
+    },
+    /// Debug a day 8 program with commands read from stdin (step, break, watch, patch, trace, ...)
+    Debug {
+        #[structopt(default_value = "inputs/day8.txt")]
+        program: String,
+    },
+}
+
+fn record_history(run: &history::Run, day: usize, args: &Opt) {
+    let records = run.records(day, &util::take_results());
+    if args.json {
+        for record in records.iter() {
+            println!("{}", serde_json::to_string(record).unwrap());
+        }
+    }
+    if args.no_history {
+        return;
+    }
+    if let Err(e) = history::append(history::HISTORY_FILE, &records) {
+        eprintln!("ERR: could not write history: {}", e);
+    }
+}
+
+// the content of a file given on the command line, errors name the file
+fn read_input(path: &str) -> Result<String, String> {
+    read_to_string(path).map_err(|e| format!("{}: {}", path, e))
 }
 
-fn print_day_header(day: usize) {
-    println!(
-        "------------------------------------ DAY {} ------------------------------------",
-        day
-    );
+fn load_history() -> Vec<history::Record> {
+    match history::load(history::HISTORY_FILE) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("ERR: {}", e);
+            vec![]
+        }
+    }
 }
 
 fn main() {
     let args = Opt::from_args();
-    let mains = [
+    if args.memory {
+        alloc::enable();
+    }
+    util::set_machine_output(args.json);
+    let mains: [fn(); 25] = [
         day1::main,
         day2::main,
         day3::main,
@@ -80,20 +299,288 @@ fn main() {
         day25::main,
     ];
 
-    match args.day.as_str() {
-        "all" => {
-            for (day, main) in mains.iter().enumerate() {
-                print_day_header(day + 1);
-                main();
-                println!();
+    let days: Vec<(usize, fn())> = mains
+        .iter()
+        .enumerate()
+        .map(|(i, &main)| (i + 1, main))
+        .collect();
+
+    if let Some(expr) = &args.expr {
+        day18::print_evaluation(
+            expr,
+            args.precedence.as_deref(),
+            args.parenthesize,
+            args.steps,
+        );
+        return;
+    }
+
+    match &args.cmd {
+        Some(Cmd::History) => {
+            history::print_history(&load_history(), args.day.parse().ok());
+            return;
+        }
+        Some(Cmd::Compare {
+            rev1,
+            rev2,
+            threshold,
+        }) => {
+            history::print_comparison(&load_history(), rev1, rev2, threshold / 100.0);
+            return;
+        }
+        Some(Cmd::Asm { source, output }) => {
+            let set = vm::InstructionSet::handheld();
+            let source = Path::new(source);
+            let output = match output {
+                Some(output) => PathBuf::from(output),
+                None => source.with_extension("bin"),
+            };
+            let result = read_to_string(source)
+                .map_err(|e| format!("{}: {}", source.display(), e))
+                .and_then(|text| {
+                    asm::assemble(&text, &set).map_err(|e| format!("{}: {}", source.display(), e))
+                })
+                .and_then(|program| {
+                    fs::write(&output, asm::encode(&program))
+                        .map_err(|e| format!("{}: {}", output.display(), e))
+                        .map(|_| program.len())
+                });
+            match result {
+                Ok(len) => println!("{} instructions written to {}", len, output.display()),
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::Disasm { program }) => {
+            let set = vm::InstructionSet::handheld();
+            match asm::load_program(Path::new(program), &set)
+                .and_then(|program| asm::disassemble(&program, &set))
+            {
+                Ok(asm) => print!("{}", asm),
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::Cfg { program }) => {
+            let set = vm::InstructionSet::handheld();
+            match asm::load_program(Path::new(program), &set) {
+                Ok(program) => print!("{}", cfg::Cfg::build(&program).to_dot(&program, &set)),
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::Debug { program }) => {
+            let set = vm::InstructionSet::handheld();
+            let program = match asm::load_program(Path::new(program), &set) {
+                Ok(program) => program,
+                Err(e) => {
+                    eprintln!("ERR: {}", e);
+                    return;
+                }
+            };
+            let mut debugger = debugger::Debugger::new(program, &set);
+            let stdin = io::stdin();
+            let prompt = stdin.is_terminal();
+            if let Err(e) = debugger::run(&mut debugger, stdin.lock(), io::stdout(), prompt) {
+                eprintln!("ERR: {}", e);
+            }
+            return;
+        }
+        Some(Cmd::Explain {
+            message,
+            rules,
+            part2,
+            dot,
+        }) => {
+            let patch = if *part2 {
+                Some(day19::PATCH_FILE)
+            } else {
+                None
+            };
+            let files =
+                read_input(rules).and_then(|rules| Ok((rules, patch.map(read_input).transpose()?)));
+            match files {
+                Ok((rules, patch)) => {
+                    day19::print_explanation(&rules, patch.as_deref(), message, *dot)
+                }
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::Monsters { tiles, monster }) => {
+            let files = read_input(tiles)
+                .and_then(|tiles| Ok((tiles, monster.as_deref().map(read_input).transpose()?)));
+            match files {
+                Ok((tiles, monster)) => day20::print_monsters(&tiles, monster.as_deref()),
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::Render {
+            tiles,
+            monster,
+            output,
+            scale,
+        }) => {
+            if *scale == 0 {
+                eprintln!("ERR: --scale must be at least 1");
+                return;
+            }
+            let files = read_input(tiles)
+                .and_then(|tiles| Ok((tiles, monster.as_deref().map(read_input).transpose()?)));
+            let result = files.and_then(|(tiles, monster)| {
+                let extension = output
+                    .as_deref()
+                    .and_then(|output| Path::new(output).extension())
+                    .and_then(|extension| extension.to_str());
+                let rendering = match extension {
+                    Some("png") => day20::render_raster(&tiles, monster.as_deref())?
+                        .scaled(*scale)
+                        .to_png(),
+                    Some("ppm") => day20::render_raster(&tiles, monster.as_deref())?
+                        .scaled(*scale)
+                        .to_ppm(),
+                    _ => day20::render_ascii(&tiles, monster.as_deref())?.into_bytes(),
+                };
+                Ok(rendering)
+            });
+            let written = result.and_then(|rendering| match output {
+                Some(output) => fs::write(output, rendering)
+                    .map(|_| println!("written to {}", output))
+                    .map_err(|e| format!("{}: {}", output, e)),
+                None => {
+                    print!("{}", String::from_utf8(rendering).unwrap());
+                    Ok(())
+                }
+            });
+            if let Err(e) = written {
+                eprintln!("ERR: {}", e);
+            }
+            return;
+        }
+        Some(Cmd::Fields { notes }) => {
+            match read_to_string(notes) {
+                Ok(notes) => day16::print_assignment(&notes),
+                Err(e) => eprintln!("ERR: {}: {}", notes, e),
+            }
+            return;
+        }
+        Some(Cmd::Ticket {
+            notes,
+            fields,
+            product,
+        }) => {
+            match read_to_string(notes) {
+                Ok(notes) => day16::print_ticket(&notes, fields, *product),
+                Err(e) => eprintln!("ERR: {}: {}", notes, e),
+            }
+            return;
+        }
+        Some(Cmd::Scan { notes, json }) => {
+            match read_to_string(notes) {
+                Ok(notes) => day16::print_scan(&notes, *json),
+                Err(e) => eprintln!("ERR: {}: {}", notes, e),
+            }
+            return;
+        }
+        Some(Cmd::Passports {
+            batch,
+            schema,
+            list,
+        }) => {
+            let files = read_input(batch)
+                .and_then(|batch| Ok((batch, schema.as_deref().map(read_input).transpose()?)));
+            match files {
+                Ok((batch, schema)) => day4::print_passports(&batch, schema.as_deref(), *list),
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        Some(Cmd::New { day }) => {
+            match scaffold::new_day(Path::new("."), *day) {
+                Ok(paths) => {
+                    for path in paths {
+                        println!("{}", path.display());
+                    }
+                }
+                Err(e) => eprintln!("ERR: {}", e),
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let selected: Vec<(usize, fn())> = match args.day.as_str() {
+        "example" => return example::main(),
+        "infi" => return infi::main(),
+        "all" => days,
+        day => match day.parse::<usize>() {
+            Ok(day) => days.into_iter().filter(|&(d, _)| d == day).collect(),
+            Err(_) => vec![],
+        },
+    };
+    if selected.is_empty() {
+        eprintln!("ERR: there is no day {}", args.day);
+        return;
+    }
+
+    match &args.cmd {
+        Some(Cmd::Watch { interval }) => {
+            watch::watch(&selected, Duration::from_millis(*interval));
+            return;
+        }
+        Some(Cmd::Fetch { base_url }) => {
+            let result = client::Client::from_env(base_url.as_deref()).and_then(|client| {
+                selected
+                    .iter()
+                    .try_for_each(|&(day, _)| client::fetch(&client, day))
+            });
+            if let Err(e) = result {
+                eprintln!("ERR: {}", e);
+            }
+            return;
+        }
+        Some(Cmd::Submit {
+            part,
+            answer,
+            base_url,
+        }) => {
+            if selected.len() != 1 {
+                eprintln!("ERR: select a single day to submit with --day");
+                return;
+            }
+            let (day, main) = selected[0];
+            let answer = match answer {
+                Some(answer) => answer.clone(),
+                None => {
+                    util::print_day_header(day);
+                    main();
+                    match util::take_results().into_iter().find(|r| r.part == *part) {
+                        Some(result) => result.answer,
+                        None => {
+                            eprintln!("ERR: day {} has no part {}", day, part);
+                            return;
+                        }
+                    }
+                }
+            };
+            let result = client::Client::from_env(base_url.as_deref())
+                .and_then(|client| client::submit(&client, day, *part, &answer));
+            if let Err(e) = result {
+                eprintln!("ERR: {}", e);
             }
+            return;
         }
-        "example" => example::main(),
-        "infi" => infi::main(),
-        _ => {
-            let day: usize = args.day.parse().unwrap();
-            print_day_header(day);
-            mains[day - 1]();
+        _ => {}
+    }
+
+    let run = history::Run::start();
+    for (i, &(day, main)) in selected.iter().enumerate() {
+        if i > 0 && !args.json {
+            println!();
         }
+        util::print_day_header(day);
+        main();
+        record_history(&run, day, &args);
     }
 }
@@ -0,0 +1,162 @@
+// Maximum matching in a bipartite graph with Hopcroft–Karp.
+// Left nodes are 0..adjacency.len(), `adjacency[l]` lists the right nodes 0..right_count next to l.
+
+use std::collections::VecDeque;
+
+const UNREACHED: usize = usize::MAX;
+
+pub struct Matching {
+    left: Vec<Option<usize>>, // partner of every left node
+    right: Vec<Option<usize>>,
+}
+
+impl Matching {
+    pub fn maximum(adjacency: &[Vec<usize>], right_count: usize) -> Self {
+        let mut matching = Matching {
+            left: vec![None; adjacency.len()],
+            right: vec![None; right_count],
+        };
+        // every phase augments along a maximal set of shortest paths
+        loop {
+            let mut dist = vec![UNREACHED; adjacency.len()];
+            let mut queue: VecDeque<usize> = (0..adjacency.len())
+                .filter(|&l| matching.left[l].is_none())
+                .collect();
+            for &l in queue.iter() {
+                dist[l] = 0;
+            }
+            let mut augmentable = false;
+            while let Some(l) = queue.pop_front() {
+                for &r in adjacency[l].iter() {
+                    match matching.right[r] {
+                        None => augmentable = true,
+                        Some(next) if dist[next] == UNREACHED => {
+                            dist[next] = dist[l] + 1;
+                            queue.push_back(next);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            if !augmentable {
+                return matching;
+            }
+            for l in 0..adjacency.len() {
+                if matching.left[l].is_none() {
+                    matching.augment(l, adjacency, &mut dist);
+                }
+            }
+        }
+    }
+
+    // depth first along the layers of `dist`
+    fn augment(&mut self, l: usize, adjacency: &[Vec<usize>], dist: &mut [usize]) -> bool {
+        for &r in adjacency[l].iter() {
+            let free = match self.right[r] {
+                None => true,
+                Some(next) => dist[next] == dist[l] + 1 && self.augment(next, adjacency, dist),
+            };
+            if free {
+                self.left[l] = Some(r);
+                self.right[r] = Some(l);
+                return true;
+            }
+        }
+        dist[l] = UNREACHED; // a dead end for the rest of this phase
+        false
+    }
+
+    pub fn partner(&self, l: usize) -> Option<usize> {
+        self.left[l]
+    }
+
+    pub fn size(&self) -> usize {
+        self.left.iter().filter(|partner| partner.is_some()).count()
+    }
+
+    /// The other right nodes `l` is matched to in some matching of the same size that keeps
+    /// every other matched left node matched.
+    pub fn alternatives(&self, adjacency: &[Vec<usize>], l: usize) -> Vec<usize> {
+        let own = match self.left[l] {
+            Some(own) => own,
+            None => return Vec::new(),
+        };
+        adjacency[l]
+            .iter()
+            .copied()
+            .filter(|&r| r != own)
+            .filter(|&r| match self.right[r] {
+                None => true,
+                // the owner of r moves on, and so on, until one takes `own` or a free node
+                Some(owner) => {
+                    let mut visited = vec![false; self.left.len()];
+                    visited[l] = true;
+                    visited[owner] = true;
+                    let mut queue = VecDeque::from(vec![owner]);
+                    while let Some(m) = queue.pop_front() {
+                        for &next in adjacency[m].iter() {
+                            if Some(next) == self.left[m] || next == r {
+                                continue;
+                            }
+                            match self.right[next] {
+                                None => return true,
+                                Some(_) if next == own => return true,
+                                Some(other) if !visited[other] => {
+                                    visited[other] = true;
+                                    queue.push_back(other);
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    false
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maximum() {
+        // a greedy matching of 0-0 leaves 1 without partner
+        let adjacency = vec![vec![0, 1], vec![0], vec![1, 2]];
+        let matching = Matching::maximum(&adjacency, 3);
+        assert_eq!(matching.size(), 3);
+        assert_eq!(
+            (0..3).map(|l| matching.partner(l)).collect::<Vec<_>>(),
+            vec![Some(1), Some(0), Some(2)]
+        );
+        assert!((0..3).all(|l| matching.alternatives(&adjacency, l).is_empty()));
+
+        // right node 0 has two candidates
+        let adjacency = vec![vec![0], vec![0], vec![1]];
+        let matching = Matching::maximum(&adjacency, 2);
+        assert_eq!(matching.size(), 2);
+        assert_eq!(matching.partner(2), Some(1));
+    }
+
+    #[test]
+    fn test_alternatives() {
+        // 0 and 1 can swap, 2 is fixed
+        let adjacency = vec![vec![0, 1], vec![0, 1, 2], vec![2]];
+        let matching = Matching::maximum(&adjacency, 3);
+        assert_eq!(matching.size(), 3);
+        assert_eq!(matching.alternatives(&adjacency, 0), vec![1]);
+        assert_eq!(matching.alternatives(&adjacency, 1), vec![0]);
+        assert_eq!(matching.alternatives(&adjacency, 2), Vec::<usize>::new());
+
+        // a free right node is an alternative, also further down a path
+        let adjacency = vec![vec![0, 1], vec![1, 2]];
+        let matching = Matching::maximum(&adjacency, 3);
+        assert_eq!(
+            (matching.partner(0), matching.partner(1)),
+            (Some(0), Some(1))
+        );
+        assert_eq!(matching.alternatives(&adjacency, 0), vec![1]);
+        assert_eq!(matching.alternatives(&adjacency, 1), vec![2]);
+    }
+}
@@ -0,0 +1,116 @@
+// A small RGB image that can be written as PPM or PNG, for pictures of puzzle grids.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+pub type Rgb = [u8; 3];
+
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>, // row by row
+}
+
+impl Raster {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Raster {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Every pixel becomes a square of `factor` by `factor` pixels.
+    pub fn scaled(&self, factor: usize) -> Self {
+        let mut scaled = Raster::new(self.width * factor, self.height * factor, [0; 3]);
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                scaled.set(x, y, self.pixels[(y / factor) * self.width + x / factor]);
+            }
+        }
+        scaled
+    }
+
+    /// Binary PPM (P6).
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(self.pixels.iter().flatten());
+        ppm
+    }
+
+    /// 8 bit truecolor PNG, without filtering.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(&(self.width as u32).to_be_bytes());
+        header.extend(&(self.height as u32).to_be_bytes());
+        header.extend(&[8, 2, 0, 0, 0]); // bit depth, color type, compression, filter, interlace
+
+        // every scanline starts with its filter type, 0 is none
+        let mut scanlines = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            scanlines.push(0);
+            scanlines.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&scanlines).unwrap(); // writing to a Vec does not fail
+        let data = encoder.finish().unwrap();
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, content) in [(b"IHDR", &header), (b"IDAT", &data), (b"IEND", &Vec::new())].iter()
+        {
+            png.extend(&(content.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend(kind.iter());
+            png.extend(content.iter());
+            let crc = crc32fast::hash(&png[start..]);
+            png.extend(&crc.to_be_bytes());
+        }
+        png
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn sample() -> Raster {
+        let mut raster = Raster::new(2, 1, [0, 0, 0]);
+        raster.set(1, 0, [255, 128, 1]);
+        raster
+    }
+
+    #[test]
+    fn test_ppm() {
+        let ppm = sample().scaled(2).to_ppm();
+        let mut expected = b"P6\n4 2\n255\n".to_vec();
+        for _ in 0..2 {
+            expected.extend(&[0, 0, 0, 0, 0, 0, 255, 128, 1, 255, 128, 1]);
+        }
+        assert_eq!(ppm, expected);
+    }
+
+    #[test]
+    fn test_png() {
+        let png = sample().to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+        // the end chunk is always the same
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut scanlines = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut scanlines)
+            .unwrap();
+        assert_eq!(scanlines, vec![0, 0, 0, 0, 255, 128, 1]);
+    }
+}
@@ -0,0 +1,278 @@
+// Generates the files for a new day: solver module, input, fixture and answers,
+// and registers the module in the `mod` lines and the `mains` array of main.rs.
+
+use std::fs::{read_to_string, remove_file, write, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The only year this crate solves.
+pub const YEAR: usize = 2020;
+
+pub struct DayFiles {
+    pub module: String,
+    pub input: String,
+    pub fixture: String,
+    pub answers: String,
+}
+
+impl DayFiles {
+    pub fn new(day: usize) -> Self {
+        DayFiles {
+            module: format!("src/day{}.rs", day),
+            input: format!("inputs/day{}.txt", day),
+            fixture: format!("inputs/day{}_test.txt", day),
+            answers: format!("inputs/day{}_answers.txt", day),
+        }
+    }
+
+    fn all(&self) -> [&str; 4] {
+        [&self.module, &self.input, &self.fixture, &self.answers]
+    }
+}
+
+/// Solver module following the same layout as the existing days, except that the known
+/// answers come from the answers file, where `submit` stores the accepted ones.
+fn template(files: &DayFiles) -> String {
+    format!(
+        r#"use crate::answers::read_known_answers;
+use crate::util::{{print_part_1, print_part_2, start_part}};
+use std::fs::read_to_string;
+
+fn solve_part_1(input: &str) -> usize {{
+    input.lines().count()
+}}
+
+fn solve_part_2(input: &str) -> usize {{
+    input.lines().count()
+}}
+
+pub fn main() {{
+    let input = read_to_string("{input}").expect("Input not found..");
+    let (known_answer_1, known_answer_2) = read_known_answers("{answers}");
+
+    // PART 1
+    let start = start_part();
+    let known_answer = known_answer_1;
+    let part_1: usize = solve_part_1(&input);
+    let duration = start.elapsed();
+    print_part_1(&part_1.to_string(), &known_answer, duration);
+
+    // PART 2
+    let start = start_part();
+    let known_answer = known_answer_2;
+    let part_2: usize = solve_part_2(&input);
+    let duration = start.elapsed();
+    print_part_2(&part_2.to_string(), &known_answer, duration);
+}}
+
+#[cfg(test)]
+mod tests {{
+    use super::*;
+
+    #[test]
+    fn test_example_1() {{
+        let input = read_to_string("{fixture}").expect("Input not found..");
+        let answer: usize = solve_part_1(&input);
+        assert_eq!(answer, 0);
+    }}
+}}
+"#,
+        input = files.input,
+        fixture = files.fixture,
+        answers = files.answers,
+    )
+}
+
+/// Day of a `mod dayN;` or `dayN::main,` line.
+fn day_of_line(line: &str, prefix: &str, suffix: &str) -> Option<usize> {
+    line.trim()
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)?
+        .parse()
+        .ok()
+}
+
+/// Adds `mod dayD;` and `dayD::main,` to the source of main.rs, keeping both lists in order.
+fn wire_main(main_src: &str, day: usize) -> Result<String, String> {
+    let mut lines: Vec<String> = main_src.lines().map(str::to_string).collect();
+    let module = format!("day{}", day);
+    let mod_days: Vec<(usize, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| Some((i, day_of_line(line, "mod day", ";")?)))
+        .collect();
+    if mod_days.iter().any(|&(_, d)| d == day) {
+        return Err(format!("main.rs already has 'mod {};'", module));
+    }
+    // rustfmt sorts the mod lines by name, so day10 comes before day2
+    let mod_at = match mod_days
+        .iter()
+        .find(|&&(_, d)| format!("day{}", d) > module)
+    {
+        Some(&(i, _)) => i,
+        None => mod_days.last().ok_or("main.rs has no 'mod dayN;' lines")?.0 + 1,
+    };
+
+    let array_start = lines
+        .iter()
+        .position(|line| line.trim().starts_with("let mains: [fn(); "))
+        .ok_or("main.rs has no 'let mains: [fn(); N] = [' line")?;
+    let array_len = lines[array_start..]
+        .iter()
+        .position(|line| line.trim() == "];")
+        .ok_or("the mains array of main.rs is not closed")?;
+    let entries: Vec<(usize, usize)> = (array_start + 1..array_start + array_len)
+        .filter_map(|i| Some((i, day_of_line(&lines[i], "day", "::main,")?)))
+        .collect();
+    if entries.len() != array_len - 1 {
+        return Err("the mains array of main.rs has lines other than 'dayN::main,'".to_string());
+    }
+    let main_at = entries
+        .iter()
+        .find(|&&(_, d)| d > day)
+        .map_or(array_start + array_len, |&(i, _)| i);
+    let indent = lines
+        .get(array_start + 1)
+        .filter(|_| !entries.is_empty())
+        .map_or("        ".to_string(), |line| {
+            line[..line.len() - line.trim_start().len()].to_string()
+        });
+
+    // from the bottom up, so the earlier indices stay valid
+    lines.insert(main_at, format!("{}{}::main,", indent, module));
+    lines[array_start] = lines[array_start].replacen(
+        &format!("[fn(); {}]", entries.len()),
+        &format!("[fn(); {}]", entries.len() + 1),
+        1,
+    );
+    lines.insert(mod_at, format!("mod {};", module));
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Creates all files of the new day below `root`, returns the created and updated paths.
+/// Nothing is written when one of the day's files already exists, and the files created
+/// so far are removed again when a later step fails.
+pub fn new_day(root: &Path, day: usize) -> Result<Vec<PathBuf>, String> {
+    if day == 0 || day > 25 {
+        return Err(format!("There is no day {} in Advent of Code..", day));
+    }
+    let files = DayFiles::new(day);
+    let existing: Vec<&str> = files
+        .all()
+        .iter()
+        .filter(|path| root.join(path).exists())
+        .copied()
+        .collect();
+    if !existing.is_empty() {
+        return Err(format!("Refusing to overwrite {}", existing.join(", ")));
+    }
+
+    // check the wiring before creating anything
+    let main_path = root.join("src/main.rs");
+    let main_src = read_to_string(&main_path).map_err(|e| format!("main.rs: {}", e))?;
+    let wired_main = wire_main(&main_src, day)?;
+
+    let contents = [
+        (&files.module, template(&files)),
+        (&files.input, String::new()),
+        (&files.fixture, String::new()),
+        (&files.answers, String::new()),
+    ];
+    let mut created = create_all(root, &contents)?;
+    if let Err(e) = write(&main_path, wired_main) {
+        remove_all(&created);
+        return Err(format!("main.rs: {}", e));
+    }
+    created.push(main_path);
+    Ok(created)
+}
+
+/// Creates every file below `root`, either all of them or, on the first error, none.
+fn create_all(root: &Path, contents: &[(&String, String)]) -> Result<Vec<PathBuf>, String> {
+    let mut created = Vec::new();
+    for (path, content) in contents.iter() {
+        let path = root.join(path);
+        let file = OpenOptions::new().write(true).create_new(true).open(&path);
+        if file.is_ok() {
+            created.push(path.clone());
+        }
+        if let Err(e) = file.and_then(|mut file| file.write_all(content.as_bytes())) {
+            remove_all(&created);
+            return Err(format!("{}: {}", path.display(), e));
+        }
+    }
+    Ok(created)
+}
+
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths.iter() {
+        let _ = remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    const MAIN: &str = "mod util;\n\nmod day1;\nmod day10;\nmod day2;\n\nfn main() {\n    let mains: [fn(); 3] = [\n        day1::main,\n        day2::main,\n        day10::main,\n    ];\n}\n";
+
+    #[test]
+    fn test_wire_main() {
+        let wired = wire_main(MAIN, 3).unwrap();
+        assert_eq!(
+            wired,
+            "mod util;\n\nmod day1;\nmod day10;\nmod day2;\nmod day3;\n\nfn main() {\n    let mains: [fn(); 4] = [\n        day1::main,\n        day2::main,\n        day3::main,\n        day10::main,\n    ];\n}\n"
+        );
+        let wired = wire_main(MAIN, 11).unwrap();
+        assert!(wired.contains("mod day10;\nmod day11;\nmod day2;\n"));
+        assert!(wired.contains("        day10::main,\n        day11::main,\n    ];\n"));
+        assert!(wire_main(&wired, 11).is_err());
+        assert!(wire_main("fn main() {}", 3).is_err());
+    }
+
+    #[test]
+    fn test_new_day() {
+        let root = std::env::temp_dir().join(format!("aoc-scaffold-{}", std::process::id()));
+        create_dir_all(root.join("src")).unwrap();
+        create_dir_all(root.join("inputs")).unwrap();
+        write(root.join("src/main.rs"), MAIN).unwrap();
+
+        let touched = new_day(&root, 3).unwrap();
+        assert_eq!(touched.len(), 5);
+        let module = read_to_string(root.join("src/day3.rs")).unwrap();
+        assert!(module.contains("read_to_string(\"inputs/day3.txt\")"));
+        assert!(module.contains("read_known_answers(\"inputs/day3_answers.txt\");"));
+        assert!(module.contains("print_part_2(&part_2.to_string(), &known_answer, duration);"));
+        assert_eq!(
+            read_to_string(root.join("inputs/day3_test.txt")).unwrap(),
+            ""
+        );
+        assert_eq!(
+            read_to_string(root.join("src/main.rs")).unwrap(),
+            wire_main(MAIN, 3).unwrap()
+        );
+
+        // never overwrite
+        write(root.join("inputs/day4.txt"), "my input").unwrap();
+        assert!(new_day(&root, 4).is_err());
+        assert_eq!(
+            read_to_string(root.join("inputs/day4.txt")).unwrap(),
+            "my input"
+        );
+        assert!(new_day(&root, 26).is_err());
+
+        // a failing wiring leaves nothing behind
+        write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        assert!(new_day(&root, 5).is_err());
+        assert!(!root.join("src/day5.rs").exists());
+
+        // neither does a failing write
+        let (kept, missing) = ("kept.txt".to_string(), "missing/dir.txt".to_string());
+        let contents = [(&kept, String::new()), (&missing, String::new())];
+        assert!(create_all(&root, &contents).is_err());
+        assert!(!root.join("kept.txt").exists());
+
+        remove_dir_all(&root).unwrap();
+    }
+}
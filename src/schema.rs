@@ -0,0 +1,308 @@
+// Declarative rules for records of `key:value` pairs, like the day 4 passports.
+//
+// One field per line, lines starting with `#` are comments:
+//   byr: range 1920-2002               a number in a range
+//   hgt: range 150-193 cm, 59-76 in    a number followed by a unit, the range depends on the unit
+//   hcl: regex #[0-9a-f]{6}            the whole value matches the regex
+//   ecl: enum amb blu brn              one of the words
+//   pid: any                           anything
+//   cid: optional                      may be missing, `optional range 1-999` checks it when it is there
+
+use regex::Regex;
+use std::fmt;
+
+enum Rule {
+    Any,
+    Range(Vec<(String, usize, usize)>), // unit, empty for a plain number, and its bounds
+    Regex(String, Regex),               // as written in the schema, and compiled
+    Enum(Vec<String>),
+}
+
+/// What is wrong with a field of a record.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    Missing,
+    Duplicate(usize), // how many times it appears
+    Malformed { value: String, expected: String },
+    OutOfRange { value: String, ranges: String },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub problem: Problem,
+}
+
+impl FieldError {
+    /// The kind of problem, without the details of the value.
+    pub fn kind(&self) -> &'static str {
+        match self.problem {
+            Problem::Missing => "missing",
+            Problem::Duplicate(_) => "duplicate",
+            Problem::Malformed { .. } => "malformed",
+            Problem::OutOfRange { .. } => "out of range",
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.problem {
+            Problem::Missing => write!(f, "{} is missing", self.field),
+            Problem::Duplicate(times) => write!(f, "{} appears {} times", self.field, times),
+            Problem::Malformed { value, expected } => write!(
+                f,
+                "{} is malformed: '{}' is not {}",
+                self.field, value, expected
+            ),
+            Problem::OutOfRange { value, ranges } => write!(
+                f,
+                "{} is out of range: {} is not in {}",
+                self.field, value, ranges
+            ),
+        }
+    }
+}
+
+impl Rule {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut split = text.splitn(2, char::is_whitespace);
+        let kind = split.next().unwrap();
+        let arg = split.next().unwrap_or_default().trim();
+        match kind {
+            "any" if arg.is_empty() => Ok(Rule::Any),
+            "range" => arg
+                .split(',')
+                .map(|range| {
+                    let mut words = range.split_whitespace();
+                    let bounds = words.next().and_then(|bounds| {
+                        let mut split = bounds.split('-').map(|n| n.parse::<usize>());
+                        match (split.next(), split.next(), split.next()) {
+                            (Some(Ok(lower)), Some(Ok(upper)), None) if lower <= upper => {
+                                Some((lower, upper))
+                            }
+                            _ => None,
+                        }
+                    });
+                    match (bounds, words.next(), words.next()) {
+                        (Some((lower, upper)), unit, None) => {
+                            Ok((unit.unwrap_or_default().to_string(), lower, upper))
+                        }
+                        _ => Err(format!("invalid range '{}'", range.trim())),
+                    }
+                })
+                .collect::<Result<Vec<_>, String>>()
+                .map(Rule::Range),
+            "regex" if !arg.is_empty() => Regex::new(&format!("^(?:{})$", arg))
+                .map(|regex| Rule::Regex(arg.to_string(), regex))
+                .map_err(|e| format!("invalid regex '{}': {}", arg, e)),
+            "enum" if !arg.is_empty() => Ok(Rule::Enum(
+                arg.split_whitespace().map(str::to_string).collect(),
+            )),
+            _ => Err(format!("invalid rule '{}'", text)),
+        }
+    }
+
+    fn check(&self, value: &str) -> Result<(), Problem> {
+        let malformed = |expected: String| Problem::Malformed {
+            value: value.to_string(),
+            expected,
+        };
+        match self {
+            Rule::Any => Ok(()),
+            Rule::Range(ranges) => {
+                let digits = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (number, unit) = value.split_at(digits);
+                let for_unit: Vec<&(String, usize, usize)> = ranges
+                    .iter()
+                    .filter(|(range_unit, _, _)| range_unit == unit)
+                    .collect();
+                let number = match number.parse::<usize>() {
+                    Ok(number) if !for_unit.is_empty() => number,
+                    _ => {
+                        let units: Vec<&str> = ranges
+                            .iter()
+                            .map(|(unit, _, _)| unit.as_str())
+                            .filter(|unit| !unit.is_empty())
+                            .collect();
+                        return Err(malformed(if units.is_empty() {
+                            "a number".to_string()
+                        } else {
+                            format!("a number followed by {}", units.join(" or "))
+                        }));
+                    }
+                };
+                if for_unit
+                    .iter()
+                    .any(|(_, lower, upper)| *lower <= number && number <= *upper)
+                {
+                    return Ok(());
+                }
+                Err(Problem::OutOfRange {
+                    value: value.to_string(),
+                    ranges: for_unit
+                        .iter()
+                        .map(|(unit, lower, upper)| format!("{}-{}{}", lower, upper, unit))
+                        .collect::<Vec<String>>()
+                        .join(" or "),
+                })
+            }
+            Rule::Regex(source, regex) if !regex.is_match(value) => {
+                Err(malformed(format!("matching {}", source)))
+            }
+            Rule::Enum(words) if !words.iter().any(|word| word == value) => {
+                Err(malformed(format!("one of {}", words.join(", "))))
+            }
+            Rule::Regex(..) | Rule::Enum(_) => Ok(()),
+        }
+    }
+}
+
+struct FieldSpec {
+    name: String,
+    required: bool,
+    rule: Rule,
+}
+
+pub struct Schema {
+    fields: Vec<FieldSpec>,
+}
+
+impl Schema {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut fields: Vec<FieldSpec> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            let mut split = line.splitn(2, ':');
+            let (name, rule) = match (split.next(), split.next()) {
+                (Some(name), Some(rule)) if !name.trim().is_empty() => (name.trim(), rule.trim()),
+                _ => return Err(error(format!("expected 'name: rule', found '{}'", line))),
+            };
+            if fields.iter().any(|field| field.name == name) {
+                return Err(error(format!("field '{}' appears twice", name)));
+            }
+            let (required, rule) = match rule.splitn(2, ' ').collect::<Vec<&str>>()[..] {
+                ["optional"] => (false, "any"),
+                ["optional", rule] => (false, rule.trim()),
+                _ => (true, rule),
+            };
+            fields.push(FieldSpec {
+                name: name.to_string(),
+                required,
+                rule: Rule::parse(rule).map_err(error)?,
+            });
+        }
+        Ok(Schema { fields })
+    }
+
+    /// Everything that is wrong with the record, field by field in the order of the schema.
+    /// Fields that are not in the schema are ignored.
+    pub fn validate(&self, record: &str) -> Vec<FieldError> {
+        let pairs: Vec<(&str, &str)> = record
+            .split_whitespace()
+            .map(|pair| {
+                let mut split = pair.splitn(2, ':');
+                (split.next().unwrap(), split.next().unwrap_or_default())
+            })
+            .collect();
+        let mut errors = Vec::new();
+        for field in self.fields.iter() {
+            let values: Vec<&str> = pairs
+                .iter()
+                .filter(|(name, _)| *name == field.name)
+                .map(|(_, value)| *value)
+                .collect();
+            let mut error = |problem| {
+                errors.push(FieldError {
+                    field: field.name.clone(),
+                    problem,
+                })
+            };
+            match values[..] {
+                [] if field.required => error(Problem::Missing),
+                [] => (),
+                [value] => {
+                    if let Err(problem) = field.rule.check(value) {
+                        error(problem);
+                    }
+                }
+                _ => error(Problem::Duplicate(values.len())),
+            }
+        }
+        errors
+    }
+
+    /// Whether the record has every required field and, when `check_values`, whether
+    /// nothing else is wrong with it either.
+    pub fn is_valid(&self, record: &str, check_values: bool) -> bool {
+        let errors = self.validate(record);
+        if check_values {
+            errors.is_empty()
+        } else {
+            errors.iter().all(|error| error.problem != Problem::Missing)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let schema = Schema::parse(
+            "# a comment\n\
+             year: range 1920-2002\n\
+             hgt: range 150-193 cm, 59-76 in\n\
+             hcl: regex #[0-9a-f]{6}\n\
+             ecl: enum amb blu\n\
+             note: optional\n\
+             cid: optional range 1-999\n",
+        )
+        .unwrap();
+        let valid = |record: &str| schema.is_valid(record, true);
+        let base = "year:1920 hgt:59in hcl:#0a0b0c ecl:blu";
+        assert!(valid(base));
+        assert!(valid(&format!("{} note:whatever cid:12", base)));
+        assert!(!valid(&format!("{} cid:1000", base)));
+        assert!(!valid("year:1920 hgt:59in hcl:#0a0b0c"));
+        assert!(schema.is_valid("year:0 hgt:cm hcl:x ecl:x", false));
+        for wrong in &[
+            "year:2003",
+            "year:19x0",
+            "hgt:59cm",
+            "hgt:59",
+            "hgt:cm",
+            "hcl:#0a0b0c0",
+            "hcl:0a0b0c",
+            "ecl:blue",
+        ] {
+            let key = wrong.split(':').next().unwrap();
+            let record: String = base
+                .split(' ')
+                .map(|pair| if pair.starts_with(key) { wrong } else { pair })
+                .collect::<Vec<&str>>()
+                .join(" ");
+            assert!(!valid(&record), "{}", record);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| Schema::parse(text).err().unwrap();
+        assert_eq!(error("a: range 5-1"), "line 1: invalid range '5-1'");
+        assert_eq!(error("\na: any\na: any"), "line 3: field 'a' appears twice");
+        assert_eq!(
+            error("a: between 1 2"),
+            "line 1: invalid rule 'between 1 2'"
+        );
+        assert_eq!(error("a"), "line 1: expected 'name: rule', found 'a'");
+        assert!(error("a: regex (").starts_with("line 1: invalid regex '('"));
+    }
+}
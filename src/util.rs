@@ -1,7 +1,11 @@
+use crate::alloc::{self, AllocStats};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 // https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
 pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
@@ -12,12 +16,94 @@ where
     Ok(io::BufReader::new(file).lines())
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartResult {
+    pub part: usize,
+    pub answer: String,
+    pub known_answer: String,
+    pub duration: Duration,
+    pub memory: Option<AllocStats>,
+}
+
+impl PartResult {
+    pub fn correct(&self) -> bool {
+        self.answer == self.known_answer
+    }
+}
+
+thread_local! {
+    // every printed answer is also kept here, so main can store it afterwards
+    static RESULTS: RefCell<Vec<PartResult>> = const { RefCell::new(Vec::new()) };
+}
+
+// set by --json, results are then printed by main as JSON instead
+static MACHINE_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_machine_output(enabled: bool) {
+    MACHINE_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns (and forgets) all part results printed since the previous call.
+pub fn take_results() -> Vec<PartResult> {
+    RESULTS.with(|results| results.borrow_mut().drain(..).collect())
+}
+
+pub fn print_day_header(day: usize) {
+    if MACHINE_OUTPUT.load(Ordering::Relaxed) {
+        return;
+    }
+    println!(
+        "------------------------------------ DAY {} ------------------------------------",
+        day
+    );
+}
+
+/// A timed part, from `start_part` until its `elapsed` is taken.
+pub struct Part {
+    start: Instant,
+}
+
+impl Part {
+    /// Also ends the allocation count of the part, so formatting the answer is not counted.
+    pub fn elapsed(&self) -> Duration {
+        let duration = self.start.elapsed();
+        alloc::stop();
+        duration
+    }
+}
+
+/// Marks the start of a timed part, also the start of its allocation count (see `--memory`).
+pub fn start_part() -> Part {
+    alloc::reset();
+    Part {
+        start: Instant::now(),
+    }
+}
+
 fn print_answer(part_nr: usize, answer: &str, known_answer: &str, duration: Duration) {
-    if answer != known_answer {
-        print!("INCORRECT || ")
+    let memory = alloc::stats();
+    if !MACHINE_OUTPUT.load(Ordering::Relaxed) {
+        if known_answer.is_empty() {
+            print!("UNVERIFIED || ")
+        } else if answer != known_answer {
+            print!("INCORRECT || ")
+        }
+        println!("PART {}: {}", part_nr, answer);
+        match memory {
+            Some(memory) => println!("\t[{:?} | {}]", duration, memory),
+            None => println!("\t[{:?}]", duration),
+        }
     }
-    println!("PART {}: {}", part_nr, answer);
-    println!("\t[{:?}]", duration);
+
+    RESULTS.with(|results| {
+        results.borrow_mut().push(PartResult {
+            part: part_nr,
+            answer: answer.to_string(),
+            known_answer: known_answer.to_string(),
+            duration,
+            memory,
+        })
+    });
 }
 
 pub fn print_part_1(answer: &str, known_answer: &str, duration: Duration) {
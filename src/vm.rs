@@ -0,0 +1,313 @@
+// Interpreter for the handheld game console of day 8.
+// Opcodes live in an `InstructionSet` table, so new ones only need a name and a function:
+// the execution loop never has to change.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Op(pub u8); // index in the instruction set
+
+impl Op {
+    // anything the parser did not recognise, halts the machine when reached
+    pub const INVALID: Op = Op(u8::MAX);
+}
+
+// opcodes of `InstructionSet::handheld`
+pub const ACC: Op = handheld_op("acc");
+pub const JMP: Op = handheld_op("jmp");
+pub const NOP: Op = handheld_op("nop");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub op: Op,
+    pub arg: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Registers {
+    pub acc: i32,
+}
+
+/// Where to continue after an instruction, relative to the current one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flow {
+    Next,
+    Jump(i32),
+}
+
+pub type Exec = fn(&mut Registers, i32) -> Flow;
+
+#[derive(Clone, Copy)]
+pub struct OpDef {
+    pub name: &'static str,
+    pub exec: Exec,
+}
+
+fn acc(regs: &mut Registers, arg: i32) -> Flow {
+    regs.acc += arg;
+    Flow::Next
+}
+
+fn jmp(_: &mut Registers, arg: i32) -> Flow {
+    Flow::Jump(arg)
+}
+
+fn nop(_: &mut Registers, _: i32) -> Flow {
+    Flow::Next
+}
+
+// the three instructions of the puzzle
+const HANDHELD: &[OpDef] = &[
+    OpDef {
+        name: "acc",
+        exec: acc,
+    },
+    OpDef {
+        name: "jmp",
+        exec: jmp,
+    },
+    OpDef {
+        name: "nop",
+        exec: nop,
+    },
+];
+
+// position of `name` in the handheld table, fails to compile when it is not there
+const fn handheld_op(name: &str) -> Op {
+    let mut i = 0;
+    while i < HANDHELD.len() {
+        let (a, b) = (HANDHELD[i].name.as_bytes(), name.as_bytes());
+        let mut same = a.len() == b.len();
+        let mut j = 0;
+        while same && j < a.len() {
+            same = a[j] == b[j];
+            j += 1;
+        }
+        if same {
+            return Op(i as u8);
+        }
+        i += 1;
+    }
+    panic!("not a handheld opcode");
+}
+
+pub struct InstructionSet {
+    ops: Vec<OpDef>,
+}
+
+impl InstructionSet {
+    /// The three instructions of the puzzle: `acc`, `jmp` and `nop`.
+    pub fn handheld() -> Self {
+        InstructionSet {
+            ops: HANDHELD.to_vec(),
+        }
+    }
+
+    /// Registers a new opcode, returns the `Op` to use in instructions.
+    #[allow(dead_code)] // the extension point, the puzzle itself only needs the handheld set
+    pub fn add(&mut self, name: &'static str, exec: Exec) -> Op {
+        assert!(
+            self.ops.len() < Op::INVALID.0 as usize,
+            "instruction set is full.."
+        );
+        self.ops.push(OpDef { name, exec });
+        Op(self.ops.len() as u8 - 1)
+    }
+
+    pub fn get(&self, op: Op) -> Option<&OpDef> {
+        self.ops.get(op.0 as usize)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Op> {
+        self.ops
+            .iter()
+            .position(|def| def.name == name)
+            .map(|i| Op(i as u8))
+    }
+
+    pub fn name(&self, op: Op) -> Option<&'static str> {
+        self.get(op).map(|def| def.name)
+    }
+
+    /// Instruction in the same notation as the puzzle input, e.g. `jmp -3`.
+    pub fn format(&self, ins: &Instruction) -> String {
+        format!("{} {:+}", self.name(ins.op).unwrap_or("???"), ins.arg)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Halt {
+    Terminated, // pc ended up right after the last instruction
+    LoopDetected { pc: usize },
+    InvalidInstruction { pc: usize },
+    PcOutOfBounds { pc: usize }, // the instruction at `pc` jumped outside the program
+    StepLimit,
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Halt::Terminated => write!(f, "terminated"),
+            Halt::LoopDetected { pc } => write!(f, "loop detected at pc {}", pc),
+            Halt::InvalidInstruction { pc } => write!(f, "invalid instruction at pc {}", pc),
+            Halt::PcOutOfBounds { pc } => write!(f, "pc {} jumps out of the program", pc),
+            Halt::StepLimit => write!(f, "step limit reached"),
+        }
+    }
+}
+
+pub struct Machine<'a> {
+    pub program: Vec<Instruction>,
+    pub set: &'a InstructionSet,
+    pub regs: Registers,
+    pub pc: usize,
+    pub steps: usize,
+    pub step_limit: Option<usize>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: Vec<Instruction>, set: &'a InstructionSet) -> Self {
+        let visited = vec![false; program.len()];
+        Machine {
+            program,
+            set,
+            regs: Registers::default(),
+            pc: 0,
+            steps: 0,
+            step_limit: None,
+            visited,
+        }
+    }
+
+    /// Why the machine would halt before executing the instruction at pc, if it would.
+    pub fn halt_reason(&self) -> Option<Halt> {
+        if self.pc == self.program.len() {
+            return Some(Halt::Terminated);
+        }
+        if self.visited[self.pc] {
+            return Some(Halt::LoopDetected { pc: self.pc });
+        }
+        if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+            return Some(Halt::StepLimit);
+        }
+        if self.set.get(self.program[self.pc].op).is_none() {
+            return Some(Halt::InvalidInstruction { pc: self.pc });
+        }
+        None
+    }
+
+    /// Executes a single instruction, or returns why that is impossible.
+    /// Nothing changes when the instruction would jump out of the program.
+    pub fn step(&mut self) -> Result<(), Halt> {
+        if let Some(halt) = self.halt_reason() {
+            return Err(halt);
+        }
+        let ins = self.program[self.pc];
+        let def = self.set.get(ins.op).unwrap(); // checked in halt_reason
+        let mut regs = self.regs;
+        let offset = match (def.exec)(&mut regs, ins.arg) {
+            Flow::Next => 1,
+            Flow::Jump(offset) => offset as isize,
+        };
+        let target = self.pc as isize + offset;
+        if target < 0 || target > self.program.len() as isize {
+            return Err(Halt::PcOutOfBounds { pc: self.pc });
+        }
+        self.regs = regs;
+        self.visited[self.pc] = true;
+        self.steps += 1;
+        self.pc = target as usize;
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> Halt {
+        loop {
+            if let Err(halt) = self.step() {
+                return halt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ins(op: Op, arg: i32) -> Instruction {
+        Instruction { op, arg }
+    }
+
+    #[test]
+    fn test_halt_reasons() {
+        let set = InstructionSet::handheld();
+        let mut machine = Machine::new(vec![ins(ACC, 3), ins(NOP, 0)], &set);
+        assert_eq!(machine.run(), Halt::Terminated);
+        assert_eq!(machine.regs.acc, 3);
+
+        let mut machine = Machine::new(vec![ins(ACC, 1), ins(JMP, -1)], &set);
+        assert_eq!(machine.run(), Halt::LoopDetected { pc: 0 });
+        assert_eq!(machine.regs.acc, 1);
+
+        let mut machine = Machine::new(vec![ins(NOP, 0), ins(Op::INVALID, 0)], &set);
+        assert_eq!(machine.run(), Halt::InvalidInstruction { pc: 1 });
+
+        let mut machine = Machine::new(vec![ins(NOP, 0), ins(JMP, -2)], &set);
+        assert_eq!(machine.run(), Halt::PcOutOfBounds { pc: 1 });
+        assert_eq!(machine.pc, 1);
+
+        // a jump out of the program also leaves the registers of that instruction untouched
+        let mut set = InstructionSet::handheld();
+        let acc_jmp = set.add("acj", |regs, arg| {
+            regs.acc += 1;
+            Flow::Jump(arg)
+        });
+        let mut machine = Machine::new(vec![ins(ACC, 5), ins(acc_jmp, 7)], &set);
+        assert_eq!(machine.run(), Halt::PcOutOfBounds { pc: 1 });
+        assert_eq!((machine.pc, machine.steps, machine.regs.acc), (1, 1, 5));
+        assert_eq!(machine.step(), Err(Halt::PcOutOfBounds { pc: 1 }));
+
+        let mut machine = Machine::new(vec![ins(ACC, 1); 10], &set);
+        machine.step_limit = Some(4);
+        assert_eq!(machine.run(), Halt::StepLimit);
+        assert_eq!(machine.regs.acc, 4);
+    }
+
+    #[test]
+    fn test_handheld_ops() {
+        let set = InstructionSet::handheld();
+        for (op, name) in [(ACC, "acc"), (JMP, "jmp"), (NOP, "nop")].iter() {
+            assert_eq!(set.lookup(name), Some(*op));
+        }
+    }
+
+    #[test]
+    fn test_custom_opcode() {
+        let mut set = InstructionSet::handheld();
+        let mul = set.add("mul", |regs, arg| {
+            regs.acc *= arg;
+            Flow::Next
+        });
+        let skp = set.add("skp", |regs, _| {
+            // skip the next instruction when acc is even
+            if regs.acc % 2 == 0 {
+                Flow::Jump(2)
+            } else {
+                Flow::Next
+            }
+        });
+        assert_eq!(set.lookup("mul"), Some(mul));
+        assert_eq!(set.name(skp), Some("skp"));
+
+        let program = vec![
+            ins(ACC, 3),
+            ins(mul, 4),
+            ins(skp, 0),
+            ins(ACC, 100),
+            ins(ACC, 1),
+        ];
+        let mut machine = Machine::new(program, &set);
+        assert_eq!(machine.run(), Halt::Terminated);
+        assert_eq!(machine.regs.acc, 13);
+    }
+}
@@ -0,0 +1,277 @@
+// Re-runs days whenever their input or fixture files change.
+// A change under `src/` rebuilds the binary and restarts it with the same arguments,
+// the answers of the last run are handed over in a file so the diffs carry on.
+
+use crate::util::{print_day_header, take_results, PartResult};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs::{metadata, read_dir, read_to_string, remove_file, write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+pub const INPUT_DIR: &str = "inputs";
+pub const SOURCE_DIR: &str = "src";
+// file with the results of the process before the restart
+const STATE_VAR: &str = "AOC_WATCH_STATE";
+
+/// Day a file in the input directory belongs to: `day19.txt` and `day19_test.txt` both give 19.
+fn day_of_file(path: &Path) -> Option<usize> {
+    let name = path.file_stem()?.to_str()?;
+    let digits: String = name
+        .strip_prefix("day")?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+pub struct Watcher {
+    dir: PathBuf,
+    days: Vec<usize>,
+    seen: HashMap<PathBuf, (SystemTime, u64)>,
+}
+
+impl Watcher {
+    pub fn new<P: AsRef<Path>>(dir: P, days: Vec<usize>) -> Self {
+        let mut watcher = Watcher {
+            dir: dir.as_ref().to_path_buf(),
+            days,
+            seen: HashMap::new(),
+        };
+        watcher.changed_files(); // initial snapshot
+        watcher
+    }
+
+    /// Scans the directory and returns the files that are new, modified or removed.
+    pub fn changed_files(&mut self) -> BTreeSet<PathBuf> {
+        let mut changed = BTreeSet::new();
+        let mut current = HashMap::new();
+        if let Ok(entries) = read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let stamp = match metadata(&path) {
+                    Ok(meta) if meta.is_file() => (
+                        meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                        meta.len(),
+                    ),
+                    _ => continue,
+                };
+                if self.seen.get(&path) != Some(&stamp) {
+                    changed.insert(path.clone());
+                }
+                current.insert(path, stamp);
+            }
+        }
+        for path in self.seen.keys() {
+            if !current.contains_key(path) {
+                changed.insert(path.clone());
+            }
+        }
+        self.seen = current;
+        changed
+    }
+
+    /// Scans the directory and returns the watched days with a new, modified or removed file.
+    pub fn changed_days(&mut self) -> BTreeSet<usize> {
+        let changed = self.changed_files();
+        changed
+            .iter()
+            .filter_map(|path| day_of_file(path))
+            .filter(|day| self.days.contains(day))
+            .collect()
+    }
+}
+
+/// Describes how a part changed since the previous run, one line per part.
+fn diff_results(previous: &[PartResult], results: &[PartResult]) -> Vec<String> {
+    results
+        .iter()
+        .map(
+            |result| match previous.iter().find(|prev| prev.part == result.part) {
+                None => format!("PART {}: {} (new)", result.part, result.answer),
+                Some(prev) => {
+                    let answer = if prev.answer == result.answer {
+                        format!("{} (unchanged)", result.answer)
+                    } else {
+                        format!("{} -> {}", prev.answer, result.answer)
+                    };
+                    let speedup = result.duration.as_secs_f64() / prev.duration.as_secs_f64();
+                    format!(
+                        "PART {}: {}\t[{:?} -> {:?}, x{:.2}]",
+                        result.part, answer, prev.duration, result.duration, speedup
+                    )
+                }
+            },
+        )
+        .collect()
+}
+
+fn run_day(day: usize, main: fn()) -> Vec<PartResult> {
+    print_day_header(day);
+    if catch_unwind(AssertUnwindSafe(main)).is_err() {
+        println!("DAY {} panicked..", day);
+    }
+    take_results()
+}
+
+fn print_changes(previous: Option<&Vec<PartResult>>, results: &[PartResult]) {
+    println!("CHANGES:");
+    for line in diff_results(previous.map_or(&[], |r| &r[..]), results) {
+        println!("\t{}", line);
+    }
+}
+
+/// The results handed over by the process before a restart, if this is one.
+fn take_state() -> Option<HashMap<usize, Vec<PartResult>>> {
+    let path = env::var_os(STATE_VAR)?;
+    env::remove_var(STATE_VAR);
+    let state = read_to_string(&path).ok();
+    let _ = remove_file(&path);
+    serde_json::from_str(&state?).ok()
+}
+
+/// Rebuilds the binary with the profile it was built with and replaces this process by the
+/// new one. Only returns when that fails, the old binary then keeps watching.
+fn rebuild_and_restart(
+    exe: &Path,
+    previous: &HashMap<usize, Vec<PartResult>>,
+) -> Result<(), String> {
+    let mut build = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    build.arg("build");
+    if !cfg!(debug_assertions) {
+        build.arg("--release");
+    }
+    let status = build.status().map_err(|e| format!("cargo build: {}", e))?;
+    if !status.success() {
+        return Err("the build failed".to_string());
+    }
+
+    let state = env::temp_dir().join(format!("aoc-watch-{}.json", process::id()));
+    let json = serde_json::to_string(previous).map_err(|e| e.to_string())?;
+    write(&state, json).map_err(|e| format!("{}: {}", state.display(), e))?;
+    let mut restart = Command::new(exe);
+    restart.args(env::args_os().skip(1)).env(STATE_VAR, &state);
+    println!("\nRestarting..");
+    #[cfg(unix)]
+    let e = std::os::unix::process::CommandExt::exec(&mut restart);
+    #[cfg(not(unix))]
+    let e = match restart.status() {
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    };
+    let _ = remove_file(&state);
+    Err(format!("restart: {}", e))
+}
+
+/// Polls the input and source directories every `interval`, re-runs the days of `days` whose
+/// input changed and restarts for source changes, forever.
+pub fn watch(days: &[(usize, fn())], interval: Duration) {
+    let before_restart = take_state();
+    // asked now, once the binary is replaced Linux reports it as "<path> (deleted)"
+    let exe = env::current_exe().ok();
+    let mut previous: HashMap<usize, Vec<PartResult>> = HashMap::new();
+    for &(day, main) in days.iter() {
+        let results = run_day(day, main);
+        if let Some(before) = &before_restart {
+            print_changes(before.get(&day), &results);
+        }
+        previous.insert(day, results);
+    }
+
+    let mut inputs = Watcher::new(INPUT_DIR, days.iter().map(|&(day, _)| day).collect());
+    // any source file may be used by any day
+    let mut sources = Watcher::new(SOURCE_DIR, vec![]);
+    println!(
+        "\nWatching {}/ and {}/ for changes..",
+        INPUT_DIR, SOURCE_DIR
+    );
+    loop {
+        sleep(interval);
+        let changed = sources.changed_files();
+        if let Some(path) = changed.iter().next() {
+            println!("\n{} changed, rebuilding..", path.display());
+            let restarted = exe
+                .as_deref()
+                .ok_or_else(|| "the path of this binary is unknown".to_string())
+                .and_then(|exe| rebuild_and_restart(exe, &previous));
+            if let Err(e) = restarted {
+                eprintln!("ERR: {}, still running the previous build", e);
+            }
+            continue;
+        }
+        for day in inputs.changed_days() {
+            let main = days.iter().find(|&&(d, _)| d == day).unwrap().1;
+            let results = run_day(day, main);
+            print_changes(previous.get(&day), &results);
+            previous.insert(day, results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, remove_file, write};
+
+    fn result(part: usize, answer: &str, millis: u64) -> PartResult {
+        PartResult {
+            part,
+            answer: answer.to_string(),
+            known_answer: answer.to_string(),
+            duration: Duration::from_millis(millis),
+            memory: None,
+        }
+    }
+
+    #[test]
+    fn test_day_of_file() {
+        assert_eq!(day_of_file(Path::new("inputs/day19.txt")), Some(19));
+        assert_eq!(day_of_file(Path::new("inputs/day19_test.txt")), Some(19));
+        assert_eq!(day_of_file(Path::new("inputs/example.txt")), None);
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let previous = vec![result(1, "5", 10), result(2, "8", 10)];
+        let results = vec![result(1, "5", 20), result(2, "9", 5)];
+        let diff = diff_results(&previous, &results);
+        assert_eq!(diff[0], "PART 1: 5 (unchanged)\t[10ms -> 20ms, x2.00]");
+        assert_eq!(diff[1], "PART 2: 8 -> 9\t[10ms -> 5ms, x0.50]");
+    }
+
+    #[test]
+    fn test_watcher_detects_changes() {
+        let dir = std::env::temp_dir().join(format!("aoc-watch-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        write(dir.join("day3.txt"), "..#").unwrap();
+        write(dir.join("day4.txt"), "byr").unwrap();
+
+        let mut watcher = Watcher::new(&dir, vec![3]);
+        assert!(watcher.changed_days().is_empty());
+
+        write(dir.join("day3_test.txt"), "#").unwrap(); // new fixture
+        write(dir.join("day4.txt"), "byr:2000").unwrap(); // day not watched
+        assert_eq!(
+            watcher.changed_days().into_iter().collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert!(watcher.changed_days().is_empty());
+
+        remove_file(dir.join("day3_test.txt")).unwrap();
+        assert_eq!(
+            watcher.changed_days().into_iter().collect::<Vec<_>>(),
+            vec![3]
+        );
+
+        write(dir.join("util.rs"), "fn main() {}").unwrap();
+        assert_eq!(
+            watcher.changed_files().into_iter().collect::<Vec<_>>(),
+            vec![dir.join("util.rs")]
+        );
+
+        remove_dir_all(&dir).unwrap();
+    }
+}